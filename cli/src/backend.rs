@@ -0,0 +1,359 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use litesvm::LiteSVM;
+use pinocchio_escrow::{client, state::DISCRIMINATOR_LEN};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::Account,
+    message::{Instruction, Message},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::Transaction,
+};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Offline state file: the compiled program plus every account the escrows need.
+/// Accounts touched by a transaction are written back after it lands.
+#[derive(Default, Deserialize, Serialize)]
+pub struct StateFile {
+    pub program: PathBuf,
+    pub accounts: BTreeMap<String, StoredAccount>,
+}
+
+impl StateFile {
+    /// Writes a state file with no accounts yet, never over an existing one.
+    pub fn create(path: &Path, program: &Path) -> Result<()> {
+        let state = StateFile {
+            program: fs::canonicalize(program)
+                .map_err(|e| format!("failed to find {}: {e}", program.display()))?,
+            accounts: BTreeMap::new(),
+        };
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+        serde_json::to_writer_pretty(file, &state)?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct StoredAccount {
+    pub lamports: u64,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    /// base64
+    pub data: String,
+}
+
+impl StoredAccount {
+    fn to_account(&self) -> Result<Account> {
+        Ok(Account {
+            lamports: self.lamports,
+            data: STANDARD.decode(&self.data)?,
+            owner: Pubkey::from_str(&self.owner)?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        })
+    }
+
+    fn from_account(account: &Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            owner: account.owner.to_string(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: STANDARD.encode(&account.data),
+        }
+    }
+}
+
+pub struct Offline {
+    path: PathBuf,
+    state: StateFile,
+    svm: LiteSVM,
+}
+
+pub enum Backend {
+    Rpc(RpcClient),
+    Offline(Box<Offline>),
+}
+
+impl Backend {
+    pub fn rpc(url: &str) -> Self {
+        Backend::Rpc(RpcClient::new(url.to_string()))
+    }
+
+    pub fn offline(path: PathBuf) -> Result<Self> {
+        let state: StateFile = serde_json::from_str(&fs::read_to_string(&path)?)?;
+
+        let mut svm = LiteSVM::new();
+        svm.add_program(client::program_id(), &fs::read(&state.program)?)
+            .map_err(|e| format!("failed to load {}: {e:?}", state.program.display()))?;
+        for (address, account) in &state.accounts {
+            svm.set_account(Pubkey::from_str(address)?, account.to_account()?)
+                .map_err(|e| format!("failed to load {address}: {e:?}"))?;
+        }
+
+        Ok(Backend::Offline(Box::new(Offline { path, state, svm })))
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        match self {
+            Backend::Rpc(rpc) => Ok(rpc
+                .get_account_with_commitment(address, rpc.commitment())?
+                .value),
+            Backend::Offline(offline) => Ok(offline.svm.get_account(address)),
+        }
+    }
+
+    /// Program accounts whose maker, the 32 bytes after the discriminator, is `maker`.
+    pub fn maker_accounts(&self, maker: &Pubkey) -> Result<Vec<(Pubkey, Account)>> {
+        match self {
+            Backend::Rpc(rpc) => {
+                let config = RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                        DISCRIMINATOR_LEN,
+                        maker.to_bytes().to_vec(),
+                    ))]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                Ok(rpc.get_program_accounts_with_config(&client::program_id(), config)?)
+            }
+            Backend::Offline(offline) => {
+                let mut accounts = Vec::new();
+                for (address, stored) in &offline.state.accounts {
+                    let account = stored.to_account()?;
+                    if account.owner == client::program_id()
+                        && account.data.get(DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + 32)
+                            == Some(maker.as_ref())
+                    {
+                        accounts.push((Pubkey::from_str(address)?, account));
+                    }
+                }
+                Ok(accounts)
+            }
+        }
+    }
+
+    /// Funds `to` with `lamports`, from the cluster's faucet over RPC.
+    pub fn airdrop(&mut self, to: &Pubkey, lamports: u64) -> Result<String> {
+        match self {
+            Backend::Rpc(rpc) => {
+                let signature = rpc.request_airdrop(to, lamports)?;
+                rpc.poll_for_signature(&signature)?;
+                Ok(signature.to_string())
+            }
+            Backend::Offline(offline) => {
+                let meta = offline
+                    .svm
+                    .airdrop(to, lamports)
+                    .map_err(|failed| format!("{:?}", failed.err))?;
+                offline.save(&[*to])?;
+                Ok(meta.signature.to_string())
+            }
+        }
+    }
+
+    /// Signs `ix` with `signer` as the fee payer and returns the signature.
+    pub fn send(&mut self, ix: Instruction, signer: &Keypair) -> Result<String> {
+        match self {
+            Backend::Rpc(rpc) => {
+                let blockhash = rpc.get_latest_blockhash()?;
+                let tx = Transaction::new(
+                    &[signer],
+                    Message::new(&[ix], Some(&signer.pubkey())),
+                    blockhash,
+                );
+                Ok(rpc.send_and_confirm_transaction(&tx)?.to_string())
+            }
+            Backend::Offline(offline) => {
+                let touched: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+                let tx = Transaction::new(
+                    &[signer],
+                    Message::new(&[ix], Some(&signer.pubkey())),
+                    offline.svm.latest_blockhash(),
+                );
+                let signature = tx.signatures[0].to_string();
+
+                let meta = offline.svm.send_transaction(tx).map_err(|failed| {
+                    format!("{:?}\n{}", failed.err, failed.meta.logs.join("\n"))
+                })?;
+                for log in &meta.logs {
+                    println!("{log}");
+                }
+
+                offline.save(&touched)?;
+                Ok(signature)
+            }
+        }
+    }
+}
+
+impl Offline {
+    fn save(&mut self, touched: &[Pubkey]) -> Result<()> {
+        for address in touched {
+            match self.svm.get_account(address) {
+                // builtin and loaded programs are recreated on load
+                Some(account) if account.executable => {}
+                Some(account) if account.lamports > 0 => {
+                    self.state
+                        .accounts
+                        .insert(address.to_string(), StoredAccount::from_account(&account));
+                }
+                _ => {
+                    self.state.accounts.remove(&address.to_string());
+                }
+            }
+        }
+
+        fs::write(&self.path, serde_json::to_string_pretty(&self.state)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Built by `cargo build-sbf` from the workspace root, the tests loading it are ignored
+    /// by default and run with `cargo test -p escrow-cli -- --ignored` once it exists
+    const PROGRAM: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/sbpf-solana-solana/release/pinocchio_escrow.so"
+    );
+
+    fn state_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("escrow-cli-{name}-{}.json", std::process::id()))
+    }
+
+    #[test]
+    fn test_stored_account_round_trip() {
+        let account = Account {
+            lamports: 42,
+            data: vec![1, 2, 3],
+            owner: client::program_id(),
+            executable: false,
+            rent_epoch: 7,
+        };
+
+        let json = serde_json::to_string(&StoredAccount::from_account(&account)).unwrap();
+        let stored: StoredAccount = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.to_account().unwrap(), account);
+    }
+
+    #[test]
+    fn test_create_state_file() {
+        let path = state_path("create");
+        let program = std::env::current_exe().unwrap();
+        StateFile::create(&path, &program).unwrap();
+
+        let state: StateFile = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(state.program, fs::canonicalize(&program).unwrap());
+        assert!(state.accounts.is_empty());
+
+        // an existing state file is left alone, and the program has to exist
+        assert!(StateFile::create(&path, &program).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(StateFile::create(&path, Path::new("missing.so")).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[ignore = "needs the program built with cargo build-sbf"]
+    fn test_offline_state_round_trip() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let maker = Pubkey::new_unique();
+        let escrow = Pubkey::new_unique();
+
+        let mut escrow_data = vec![0u8; DISCRIMINATOR_LEN + 32];
+        escrow_data[DISCRIMINATOR_LEN..].copy_from_slice(maker.as_ref());
+        let wallet = Account {
+            lamports: 10_000_000_000,
+            data: vec![],
+            owner: Pubkey::default(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let escrow_account = Account {
+            lamports: 1_000_000_000,
+            data: escrow_data,
+            owner: client::program_id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+
+        let path = state_path("round-trip");
+        let state = StateFile {
+            program: PathBuf::from(PROGRAM),
+            accounts: BTreeMap::from([
+                (
+                    payer.pubkey().to_string(),
+                    StoredAccount::from_account(&wallet),
+                ),
+                (
+                    escrow.to_string(),
+                    StoredAccount::from_account(&escrow_account),
+                ),
+            ]),
+        };
+        fs::write(&path, serde_json::to_string(&state).unwrap()).unwrap();
+
+        let mut backend = Backend::offline(path.clone()).unwrap();
+        assert!(backend.get_account(&payer.pubkey()).unwrap().is_some());
+        let accounts = backend.maker_accounts(&maker).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, escrow);
+
+        // system program Transfer, its bincode tag is 2
+        let mut data = vec![2, 0, 0, 0];
+        data.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        let ix = Instruction {
+            program_id: Pubkey::default(),
+            accounts: vec![
+                solana_sdk::message::AccountMeta::new(payer.pubkey(), true),
+                solana_sdk::message::AccountMeta::new(recipient, false),
+            ],
+            data,
+        };
+        backend.send(ix, &payer).unwrap();
+
+        // the touched accounts are written back and load into a fresh backend
+        let mut backend = Backend::offline(path.clone()).unwrap();
+        let account = backend.get_account(&recipient).unwrap().unwrap();
+        assert_eq!(account.lamports, 1_000_000_000);
+        assert!(backend.get_account(&escrow).unwrap().is_some());
+
+        let funded = Pubkey::new_unique();
+        backend.airdrop(&funded, 5_000).unwrap();
+        let backend = Backend::offline(path.clone()).unwrap();
+        assert_eq!(
+            backend.get_account(&funded).unwrap().unwrap().lamports,
+            5_000
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,518 @@
+//! Command-line tool to inspect and act on escrows over JSON-RPC, or offline against a
+//! litesvm instance loaded from a state file (see [`backend::StateFile`]).
+
+mod backend;
+
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, Subcommand};
+use pinocchio::error::ProgramError;
+use pinocchio_escrow::{
+    client,
+    events::EscrowEvent,
+    state::{
+        designated_taker, escrow_extensions, expiry, extensions, AccountType, Escrow,
+        ExtensionType, WincodeEscrow,
+    },
+};
+use serde::Serialize;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+};
+
+use crate::backend::{Backend, Result, StateFile};
+
+#[derive(Parser)]
+#[command(name = "escrow-cli", about = "Inspect and act on pinocchio escrows")]
+struct Cli {
+    /// JSON-RPC endpoint
+    #[arg(long, global = true, default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    /// Run against the litesvm state in this file instead of the RPC endpoint. `init`
+    /// creates it and `airdrop` funds wallets in it. The file is JSON: `program`, the path
+    /// of the compiled program, and `accounts`, a map from address to `lamports`, `owner`,
+    /// `executable`, `rent_epoch` and base64 `data`
+    #[arg(long, global = true)]
+    offline: Option<PathBuf>,
+
+    /// Signer keypair, defaults to ~/.config/solana/id.json
+    #[arg(long, global = true)]
+    keypair: Option<PathBuf>,
+
+    /// Print escrows from `show` and `list` as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the `--offline` state file, loading the compiled program from `program`
+    Init { program: PathBuf },
+    /// Fund a wallet, from the faucet over RPC
+    Airdrop {
+        to: Pubkey,
+        #[arg(long, default_value_t = 1_000_000_000)]
+        lamports: u64,
+    },
+    /// Lock `give` of mint_a for `receive` of mint_b, signed by the maker
+    Make {
+        #[arg(long)]
+        mint_a: Pubkey,
+        #[arg(long)]
+        mint_b: Pubkey,
+        #[arg(long)]
+        receive: u64,
+        #[arg(long)]
+        give: u64,
+    },
+    /// Take an escrow, signed by the taker
+    Take { escrow: Pubkey },
+    /// Refund the signer's escrow
+    Refund,
+    /// Print an escrow account
+    Show { escrow: Pubkey },
+    /// Print every escrow of a maker
+    List {
+        #[arg(long)]
+        maker: Pubkey,
+    },
+    /// Decode base64 escrow account data or a `Program data:` event
+    Decode { data: String },
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    // the state file doesn't exist yet, so there is no backend to load
+    if let Command::Init { program } = &cli.command {
+        let path = cli.offline.ok_or("init needs --offline <state file>")?;
+        StateFile::create(&path, program)?;
+        println!("created {}", path.display());
+        return Ok(());
+    }
+
+    let mut backend = match cli.offline {
+        Some(path) => Backend::offline(path)?,
+        None => Backend::rpc(&cli.url),
+    };
+
+    match cli.command {
+        Command::Init { .. } => unreachable!("handled before loading the backend"),
+        Command::Airdrop { to, lamports } => println!("{}", backend.airdrop(&to, lamports)?),
+        Command::Make {
+            mint_a,
+            mint_b,
+            receive,
+            give,
+        } => {
+            let signer = signer(cli.keypair)?;
+            let ix = client::make_ix(&signer.pubkey(), &mint_a, &mint_b, receive, give);
+            let (escrow, _) = client::find_escrow_address(&signer.pubkey());
+            println!("{}", backend.send(ix, &signer)?);
+            println!("escrow: {escrow}");
+        }
+        Command::Take { escrow } => {
+            let signer = signer(cli.keypair)?;
+            let (state, _) = fetch_escrow(&backend, &escrow)?;
+            let maker = state.maker();
+            if client::find_escrow_address(&maker).0 != escrow {
+                return Err(format!("{escrow} is not the escrow of its maker {maker}").into());
+            }
+
+            let ix = client::take_ix(&signer.pubkey(), &maker, &state.mint_a(), &state.mint_b());
+            println!("{}", backend.send(ix, &signer)?);
+        }
+        Command::Refund => {
+            let signer = signer(cli.keypair)?;
+            let (escrow, _) = client::find_escrow_address(&signer.pubkey());
+            let (state, _) = fetch_escrow(&backend, &escrow)?;
+
+            let ix = client::refund_ix(&signer.pubkey(), &state.mint_a());
+            println!("{}", backend.send(ix, &signer)?);
+        }
+        Command::Show { escrow } => {
+            let (state, data) = fetch_escrow(&backend, &escrow)?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&state)?);
+            } else {
+                println!("escrow:            {escrow}");
+                print_escrow(&state, &data);
+            }
+        }
+        Command::List { maker } => {
+            let accounts = backend.maker_accounts(&maker)?;
+            if accounts.is_empty() {
+                println!("no escrows for {maker}");
+            }
+            for (address, account) in accounts {
+                match EscrowAccount::try_from_bytes(&account.data) {
+                    Ok(state) if cli.json => {
+                        println!(
+                            "{}",
+                            serde_json::json!({ "escrow": address.to_string(), "state": state })
+                        );
+                    }
+                    Ok(state) => {
+                        println!("escrow:            {address}");
+                        print_escrow(&state, &account.data);
+                        println!();
+                    }
+                    Err(_) => println!("{address}: {} bytes, not an escrow", account.data.len()),
+                }
+            }
+        }
+        Command::Decode { data } => {
+            let data = STANDARD.decode(data.trim())?;
+            if let Ok(event) = EscrowEvent::try_from_bytes(&data) {
+                print_event(&event);
+            } else if let Ok(state) = EscrowAccount::try_from_bytes(&data) {
+                print_escrow(&state, &data);
+            } else {
+                return Err(
+                    format!("{} bytes are neither an event nor an escrow", data.len()).into(),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn signer(path: Option<PathBuf>) -> Result<Keypair> {
+    let path = match path {
+        Some(path) => path,
+        None => PathBuf::from(std::env::var("HOME")?).join(".config/solana/id.json"),
+    };
+    read_keypair_file(&path).map_err(|e| format!("failed to read {}: {e}", path.display()).into())
+}
+
+fn pubkey(address: pinocchio::Address) -> Pubkey {
+    Pubkey::new_from_array(address.to_bytes())
+}
+
+/// Escrow of either layout, told apart by the discriminator the way the program routes
+/// Take and Refund to their v2 handlers.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum EscrowAccount {
+    Escrow(Escrow),
+    WincodeEscrow(WincodeEscrow),
+}
+
+impl EscrowAccount {
+    fn try_from_bytes(data: &[u8]) -> std::result::Result<Self, ProgramError> {
+        match AccountType::from_data(data)? {
+            AccountType::Escrow => Escrow::try_from_bytes(data).map(Self::Escrow),
+            AccountType::WincodeEscrow => {
+                WincodeEscrow::try_from_bytes(data).map(Self::WincodeEscrow)
+            }
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    fn maker(&self) -> Pubkey {
+        match self {
+            Self::Escrow(escrow) => pubkey(escrow.maker()),
+            Self::WincodeEscrow(escrow) => Pubkey::new_from_array(escrow.maker),
+        }
+    }
+
+    fn mint_a(&self) -> Pubkey {
+        match self {
+            Self::Escrow(escrow) => pubkey(escrow.mint_a()),
+            Self::WincodeEscrow(escrow) => Pubkey::new_from_array(escrow.mint_a),
+        }
+    }
+
+    fn mint_b(&self) -> Pubkey {
+        match self {
+            Self::Escrow(escrow) => pubkey(escrow.mint_b()),
+            Self::WincodeEscrow(escrow) => Pubkey::new_from_array(escrow.mint_b),
+        }
+    }
+
+    fn amounts(&self) -> (u64, u64) {
+        match self {
+            Self::Escrow(escrow) => (escrow.amount_to_give(), escrow.amount_to_receive()),
+            Self::WincodeEscrow(escrow) => (escrow.amount_to_give(), escrow.amount_to_receive()),
+        }
+    }
+}
+
+/// The escrow and its raw account data, which holds the extensions.
+fn fetch_escrow(backend: &Backend, escrow: &Pubkey) -> Result<(EscrowAccount, Vec<u8>)> {
+    let account = backend
+        .get_account(escrow)?
+        .ok_or_else(|| format!("{escrow} does not exist"))?;
+    if account.owner != client::program_id() {
+        return Err(format!("{escrow} is not owned by the escrow program").into());
+    }
+    let state = EscrowAccount::try_from_bytes(&account.data)
+        .map_err(|e| format!("{escrow} is not an escrow: {e:?}"))?;
+    Ok((state, account.data))
+}
+
+fn print_escrow(state: &EscrowAccount, data: &[u8]) {
+    let (amount_to_give, amount_to_receive) = state.amounts();
+    println!("maker:             {}", state.maker());
+    println!("mint_a:            {}", state.mint_a());
+    println!("mint_b:            {}", state.mint_b());
+    println!("amount_to_give:    {amount_to_give}");
+    println!("amount_to_receive: {amount_to_receive}");
+    // MakeV2 escrows have no options or extensions
+    let EscrowAccount::Escrow(escrow) = state else {
+        println!("layout:            make_v2");
+        return;
+    };
+    if escrow.has_taker() {
+        println!("taker:             {}", pubkey(escrow.taker()));
+    }
+    if escrow.is_vesting() {
+        println!(
+            "vesting:           cliff {}s, duration {}s, claimed {}",
+            escrow.vesting_cliff(),
+            escrow.vesting_duration(),
+            escrow.claimed()
+        );
+    }
+    if escrow.has_arbiter() {
+        println!("arbiter:           {}", pubkey(escrow.arbiter()));
+    }
+    if escrow.approval_threshold > 0 {
+        println!("approvals needed:  {}", escrow.approval_threshold);
+    }
+    if escrow.is_htlc() {
+        println!(
+            "htlc:              recipient {}, timeout {}",
+            pubkey(escrow.htlc_recipient()),
+            escrow.htlc_timeout()
+        );
+    }
+    if escrow.has_delegate() {
+        println!("delegate:          {}", pubkey(escrow.delegate()));
+    }
+    println!("listed:            {}", escrow.is_listed());
+    for entry in extensions(escrow_extensions(data)) {
+        match entry {
+            Ok((ExtensionType::Expiry, value)) => {
+                println!("expiry:            {}", expiry(value))
+            }
+            Ok((ExtensionType::DesignatedTaker, value)) => {
+                println!("designated taker:  {}", pubkey(designated_taker(value)))
+            }
+            Ok((ExtensionType::Memo, value)) => {
+                println!("memo:              {}", String::from_utf8_lossy(value))
+            }
+            Err(_) => println!("extensions:        malformed"),
+        }
+    }
+}
+
+fn print_event(event: &EscrowEvent) {
+    println!("event:             {:?}", event.kind);
+    println!("slot:              {}", event.slot);
+    println!("escrow:            {}", pubkey(event.escrow));
+    println!("maker:             {}", pubkey(event.maker));
+    println!("taker:             {}", pubkey(event.taker));
+    println!("mint_a:            {}", pubkey(event.mint_a));
+    println!("mint_b:            {}", pubkey(event.mint_b));
+    println!("amount_to_give:    {}", event.amount_to_give);
+    println!("amount_to_receive: {}", event.amount_to_receive);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::backend::StoredAccount;
+
+    /// Built by `cargo build-sbf` from the workspace root, see `backend::tests::PROGRAM`
+    const PROGRAM: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/sbpf-solana-solana/release/pinocchio_escrow.so"
+    );
+
+    /// A MakeV2 escrow giving 500 of `mint_a` for 100 of `mint_b`, as stored on chain
+    fn v2_escrow_data(maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey) -> Vec<u8> {
+        let widen = |amount: u64| {
+            let mut field = [0u8; 64];
+            field[..8].copy_from_slice(&amount.to_le_bytes());
+            field
+        };
+
+        let mut data = AccountType::WincodeEscrow.discriminator().to_vec();
+        data.extend_from_slice(maker.as_ref());
+        data.extend_from_slice(mint_a.as_ref());
+        data.extend_from_slice(mint_b.as_ref());
+        data.extend_from_slice(&widen(100));
+        data.extend_from_slice(&widen(500));
+        data.push(client::find_escrow_address(maker).1);
+        data
+    }
+
+    #[test]
+    fn test_cli_args() {
+        Cli::command().debug_assert();
+
+        let escrow = Pubkey::new_unique();
+        let cli = Cli::try_parse_from([
+            "escrow-cli",
+            "show",
+            &escrow.to_string(),
+            "--json",
+            "--offline",
+            "state.json",
+        ])
+        .unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.offline, Some(PathBuf::from("state.json")));
+        assert_eq!(cli.url, "http://127.0.0.1:8899");
+        assert!(matches!(cli.command, Command::Show { escrow: e } if e == escrow));
+
+        let mint = Pubkey::new_unique().to_string();
+        let make = |receive: &str| {
+            Cli::try_parse_from([
+                "escrow-cli",
+                "make",
+                "--mint-a",
+                &mint,
+                "--mint-b",
+                &mint,
+                "--receive",
+                receive,
+                "--give",
+                "500",
+            ])
+        };
+        assert!(matches!(
+            make("100").unwrap().command,
+            Command::Make {
+                receive: 100,
+                give: 500,
+                ..
+            }
+        ));
+        assert!(make("-1").is_err());
+        assert!(Cli::try_parse_from(["escrow-cli", "take", "not-a-pubkey"]).is_err());
+    }
+
+    #[test]
+    fn test_decode_v2_escrow() {
+        let (maker, mint_a, mint_b) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let data = v2_escrow_data(&maker, &mint_a, &mint_b);
+
+        let state = EscrowAccount::try_from_bytes(&data).unwrap();
+        assert!(matches!(state, EscrowAccount::WincodeEscrow(_)));
+        assert_eq!(state.maker(), maker);
+        assert_eq!(state.mint_a(), mint_a);
+        assert_eq!(state.mint_b(), mint_b);
+        assert_eq!(state.amounts(), (500, 100));
+
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["maker"], maker.to_string());
+        assert_eq!(json["amount_to_give"], 500);
+
+        assert!(EscrowAccount::try_from_bytes(&data[..data.len() - 1]).is_err());
+
+        let cli = Cli::try_parse_from(["escrow-cli", "decode", &STANDARD.encode(data)]).unwrap();
+        run(cli).unwrap();
+    }
+
+    #[test]
+    fn test_init_state_file() {
+        let path =
+            std::env::temp_dir().join(format!("escrow-cli-init-{}.json", std::process::id()));
+        let program = std::env::current_exe().unwrap();
+        let program = program.to_str().unwrap();
+
+        // init writes the state file `--offline` points at
+        assert!(run(Cli::try_parse_from(["escrow-cli", "init", program]).unwrap()).is_err());
+        let offline = path.to_str().unwrap();
+        let init = || Cli::try_parse_from(["escrow-cli", "--offline", offline, "init", program]);
+        run(init().unwrap()).unwrap();
+        assert!(path.exists());
+        assert!(run(init().unwrap()).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs the program built with cargo build-sbf"]
+    fn test_offline_show_and_list_v2_escrow() {
+        let maker = Pubkey::new_unique();
+        let (escrow, _) = client::find_escrow_address(&maker);
+        let data = v2_escrow_data(&maker, &Pubkey::new_unique(), &Pubkey::new_unique());
+
+        let path =
+            std::env::temp_dir().join(format!("escrow-cli-show-{}.json", std::process::id()));
+        let state = StateFile {
+            program: PathBuf::from(PROGRAM),
+            accounts: BTreeMap::from([(
+                escrow.to_string(),
+                StoredAccount {
+                    lamports: 1_000_000_000,
+                    owner: client::program_id().to_string(),
+                    executable: false,
+                    rent_epoch: 0,
+                    data: STANDARD.encode(data),
+                },
+            )]),
+        };
+        std::fs::write(&path, serde_json::to_string(&state).unwrap()).unwrap();
+
+        let offline = path.to_str().unwrap();
+        for args in [
+            vec![
+                "escrow-cli",
+                "--offline",
+                offline,
+                "show",
+                &escrow.to_string(),
+            ],
+            vec![
+                "escrow-cli",
+                "--offline",
+                offline,
+                "--json",
+                "show",
+                &escrow.to_string(),
+            ],
+            vec![
+                "escrow-cli",
+                "--offline",
+                offline,
+                "list",
+                "--maker",
+                &maker.to_string(),
+            ],
+        ] {
+            run(Cli::try_parse_from(args).unwrap()).unwrap();
+        }
+
+        // an account that isn't there is an error, not a panic
+        let missing = Pubkey::new_unique().to_string();
+        let cli = Cli::try_parse_from(["escrow-cli", "--offline", offline, "show", &missing]);
+        assert!(run(cli.unwrap()).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+//! Off-chain instruction builders, enabled with the `client` feature.
+//! Every builder derives the escrow PDA and the associated token accounts it touches
+//! and only marks the accounts the program writes to as writable.
+
+use pinocchio::Address;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+use crate::{
+    instructions::{wincode_v2, EscrowInstruction, MakeArgs, MakeInstructionData},
+    pda,
+    state::ExtensionType,
+};
+
+pub fn program_id() -> Pubkey {
+    Pubkey::new_from_array(crate::ID.to_bytes())
+}
+
+pub fn token_program_id() -> Pubkey {
+    Pubkey::new_from_array(pinocchio_token::ID.to_bytes())
+}
+
+pub fn associated_token_program_id() -> Pubkey {
+    Pubkey::new_from_array(pinocchio_associated_token_account::ID.to_bytes())
+}
+
+pub fn system_program_id() -> Pubkey {
+    Pubkey::new_from_array(pinocchio_system::ID.to_bytes())
+}
+
+/// Escrow PDA of `maker` and its bump.
+pub fn find_escrow_address(maker: &Pubkey) -> (Pubkey, u8) {
+    let (escrow, bump) = pda::find_escrow_address(&Address::from(maker.to_bytes()));
+    (Pubkey::new_from_array(escrow.to_bytes()), bump)
+}
+
+/// Vault of `escrow` holding its `mint` side of the trade.
+pub fn escrow_vault_address(escrow: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let vault = pda::escrow_vault_address(
+        &Address::from(escrow.to_bytes()),
+        &Address::from(mint.to_bytes()),
+    );
+    Pubkey::new_from_array(vault.to_bytes())
+}
+
+/// Associated token account of `wallet` for `mint` under the classic token program.
+pub fn find_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program_id().as_ref(), mint.as_ref()],
+        &associated_token_program_id(),
+    )
+    .0
+}
+
+fn make_accounts(
+    maker: &Pubkey,
+    escrow: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new(*escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new_readonly(*mint_b, false),
+        AccountMeta::new(find_associated_token_address(maker, mint_a), false),
+        AccountMeta::new(escrow_vault_address(escrow, mint_a), false),
+        AccountMeta::new_readonly(system_program_id(), false),
+        AccountMeta::new_readonly(token_program_id(), false),
+        AccountMeta::new_readonly(associated_token_program_id(), false),
+    ]
+}
+
+/// Plain v1 Make, `maker` locks `amount_to_give` of mint_a for `amount_to_receive` of mint_b.
+pub fn make_ix(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) -> Instruction {
+    let (escrow, bump) = find_escrow_address(maker);
+
+    Instruction {
+        program_id: program_id(),
+        accounts: make_accounts(maker, &escrow, mint_a, mint_b),
+        data: EscrowInstruction::Make(make_args(bump, amount_to_receive, amount_to_give)).pack(),
+    }
+}
+
+/// v1 Make with the rent paid by `payer`, `maker` only signs for the mint_a transfer.
+pub fn make_with_payer_ix(
+    payer: &Pubkey,
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) -> Instruction {
+    let (escrow, bump) = find_escrow_address(maker);
+
+    let mut accounts = make_accounts(maker, &escrow, mint_a, mint_b);
+    accounts[0] = AccountMeta::new_readonly(*maker, true);
+    accounts.insert(0, AccountMeta::new(*payer, true));
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: EscrowInstruction::MakeWithPayer(make_args(bump, amount_to_receive, amount_to_give))
+            .pack(),
+    }
+}
+
+fn make_args(bump: u8, amount_to_receive: u64, amount_to_give: u64) -> MakeArgs<'static> {
+    MakeArgs {
+        data: MakeInstructionData {
+            bump,
+            amount_to_receive,
+            amount_to_give,
+        },
+        options: None,
+        approvers: &[],
+    }
+}
+
+/// v1 Make that also stores `extensions` after the escrow, given as (type, value) pairs.
+pub fn make_with_extensions_ix(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+    extensions: &[(ExtensionType, &[u8])],
+) -> Instruction {
+    let mut entries = Vec::new();
+    for (extension_type, value) in extensions {
+        entries.extend_from_slice(&(*extension_type as u16).to_le_bytes());
+        entries.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        entries.extend_from_slice(value);
+    }
+
+    let (escrow, bump) = find_escrow_address(maker);
+
+    Instruction {
+        program_id: program_id(),
+        accounts: make_accounts(maker, &escrow, mint_a, mint_b),
+        data: EscrowInstruction::MakeWithExtensions {
+            extensions: &entries,
+            make: make_args(bump, amount_to_receive, amount_to_give),
+        }
+        .pack(),
+    }
+}
+
+/// Make through the wincode encoded v2 path, amounts are widened to its 64 byte fields.
+pub fn make_v2_ix(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) -> Instruction {
+    let (escrow, bump) = find_escrow_address(maker);
+
+    let widen = |amount: u64| {
+        let mut field = [0u8; 64];
+        field[..8].copy_from_slice(&amount.to_le_bytes());
+        field
+    };
+
+    Instruction {
+        program_id: program_id(),
+        accounts: make_accounts(maker, &escrow, mint_a, mint_b),
+        data: EscrowInstruction::MakeV2(wincode_v2::make::MakeInstructionData {
+            bump,
+            amount_to_receive: widen(amount_to_receive),
+            amount_to_give: widen(amount_to_give),
+        })
+        .pack(),
+    }
+}
+
+/// Take of a plain escrow. Missing taker and maker token accounts are created by the program.
+pub fn take_ix(taker: &Pubkey, maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey) -> Instruction {
+    let (escrow, _) = find_escrow_address(maker);
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*taker, true),
+            AccountMeta::new(*maker, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(*mint_a, false),
+            AccountMeta::new_readonly(*mint_b, false),
+            AccountMeta::new(find_associated_token_address(taker, mint_a), false),
+            AccountMeta::new(find_associated_token_address(taker, mint_b), false),
+            AccountMeta::new(escrow_vault_address(&escrow, mint_a), false),
+            AccountMeta::new(find_associated_token_address(maker, mint_b), false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(system_program_id(), false),
+            AccountMeta::new_readonly(associated_token_program_id(), false),
+        ],
+        data: EscrowInstruction::Take.pack(),
+    }
+}
+
+/// Refund of an unlisted escrow signed by the maker, mint_a goes back to the maker's
+/// associated token account.
+pub fn refund_ix(maker: &Pubkey, mint_a: &Pubkey) -> Instruction {
+    let (escrow, _) = find_escrow_address(maker);
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new(find_associated_token_address(maker, mint_a), false),
+            AccountMeta::new(escrow_vault_address(&escrow, mint_a), false),
+            AccountMeta::new_readonly(token_program_id(), false),
+            AccountMeta::new_readonly(system_program_id(), false),
+            AccountMeta::new_readonly(associated_token_program_id(), false),
+            // lets the program recreate the maker's account if it was closed
+            AccountMeta::new_readonly(*mint_a, false),
+        ],
+        data: EscrowInstruction::Refund.pack(),
+    }
+}
+
+/// Migrate of an escrow written before account discriminators, `maker` covers the extra rent.
+pub fn migrate_ix(maker: &Pubkey, escrow: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(*escrow, false),
+            AccountMeta::new_readonly(system_program_id(), false),
+        ],
+        data: EscrowInstruction::Migrate.pack(),
+    }
+}
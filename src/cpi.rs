@@ -0,0 +1,245 @@
+//! Instructions for other on-chain programs to invoke the escrow through CPI, in the
+//! style of `pinocchio_token::instructions`. Depend on the crate with the
+//! `no-entrypoint` feature to use them.
+
+use pinocchio::{
+    cpi::{invoke_signed, Signer},
+    instruction::{InstructionAccount, InstructionView},
+    AccountView, ProgramResult,
+};
+
+use crate::instructions::{codec::write_payload_into, EscrowInstrctions, MakeInstructionData};
+
+/// v1 Make through MakeWithPayer, locks `amount_to_give` of mint_a in the escrow vault
+/// for `amount_to_receive` of mint_b. The payer covers the escrow and vault rent, so the
+/// maker can be a PDA of the calling program signing through `invoke_signed`. Pass the
+/// maker as the payer too when it is a wallet funding its own escrow.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[SIGNER]` Maker
+///   2. `[WRITE]` Escrow PDA of the maker
+///   3. `[]` Mint A
+///   4. `[]` Mint B
+///   5. `[WRITE]` Maker's mint_a token account
+///   6. `[WRITE]` Escrow vault, the escrow's associated token account for mint_a
+///   7. `[]` System program
+///   8. `[]` Token program
+///   9. `[]` Associated token program
+pub struct Make<'a> {
+    pub payer: &'a AccountView,
+    pub maker: &'a AccountView,
+    pub escrow: &'a AccountView,
+    pub mint_a: &'a AccountView,
+    pub mint_b: &'a AccountView,
+    pub maker_ata: &'a AccountView,
+    pub escrow_ata: &'a AccountView,
+    pub system_program: &'a AccountView,
+    pub token_program: &'a AccountView,
+    pub associated_token_program: &'a AccountView,
+    /// Bump of the escrow PDA
+    pub bump: u8,
+    pub amount_to_receive: u64,
+    pub amount_to_give: u64,
+}
+
+impl Make<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas: [InstructionAccount; 10] = [
+            InstructionAccount::writable_signer(self.payer.address()),
+            InstructionAccount::readonly_signer(self.maker.address()),
+            InstructionAccount::writable(self.escrow.address()),
+            InstructionAccount::readonly(self.mint_a.address()),
+            InstructionAccount::readonly(self.mint_b.address()),
+            InstructionAccount::writable(self.maker_ata.address()),
+            InstructionAccount::writable(self.escrow_ata.address()),
+            InstructionAccount::readonly(self.system_program.address()),
+            InstructionAccount::readonly(self.token_program.address()),
+            InstructionAccount::readonly(self.associated_token_program.address()),
+        ];
+
+        // descriminator, then the Make payload without the optional tail
+        let mut instruction_data = [0u8; 1 + MakeInstructionData::LEN];
+        instruction_data[0] = EscrowInstrctions::MakeWithPayer as u8;
+        write_payload_into(
+            &mut instruction_data[1..],
+            &MakeInstructionData {
+                bump: self.bump,
+                amount_to_receive: self.amount_to_receive,
+                amount_to_give: self.amount_to_give,
+            },
+        )?;
+
+        let instruction = InstructionView {
+            program_id: &crate::ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.payer,
+                self.maker,
+                self.escrow,
+                self.mint_a,
+                self.mint_b,
+                self.maker_ata,
+                self.escrow_ata,
+                self.system_program,
+                self.token_program,
+                self.associated_token_program,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Take of a plain escrow, the taker pays mint_b to the maker and receives the vault's
+/// mint_a. Missing taker and maker token accounts are created by the escrow program.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Taker
+///   1. `[WRITE]` Maker
+///   2. `[WRITE]` Escrow PDA of the maker
+///   3. `[]` Mint A
+///   4. `[]` Mint B
+///   5. `[WRITE]` Taker's mint_a token account
+///   6. `[WRITE]` Taker's mint_b token account
+///   7. `[WRITE]` Escrow vault
+///   8. `[WRITE]` Maker's mint_b token account
+///   9. `[]` Token program
+///  10. `[]` System program
+///  11. `[]` Associated token program
+pub struct Take<'a> {
+    pub taker: &'a AccountView,
+    pub maker: &'a AccountView,
+    pub escrow: &'a AccountView,
+    pub mint_a: &'a AccountView,
+    pub mint_b: &'a AccountView,
+    pub taker_ata_a: &'a AccountView,
+    pub taker_ata_b: &'a AccountView,
+    pub escrow_ata: &'a AccountView,
+    pub maker_ata_b: &'a AccountView,
+    pub token_program: &'a AccountView,
+    pub system_program: &'a AccountView,
+    pub associated_token_program: &'a AccountView,
+}
+
+impl Take<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas: [InstructionAccount; 12] = [
+            InstructionAccount::writable_signer(self.taker.address()),
+            InstructionAccount::writable(self.maker.address()),
+            InstructionAccount::writable(self.escrow.address()),
+            InstructionAccount::readonly(self.mint_a.address()),
+            InstructionAccount::readonly(self.mint_b.address()),
+            InstructionAccount::writable(self.taker_ata_a.address()),
+            InstructionAccount::writable(self.taker_ata_b.address()),
+            InstructionAccount::writable(self.escrow_ata.address()),
+            InstructionAccount::writable(self.maker_ata_b.address()),
+            InstructionAccount::readonly(self.token_program.address()),
+            InstructionAccount::readonly(self.system_program.address()),
+            InstructionAccount::readonly(self.associated_token_program.address()),
+        ];
+
+        let instruction = InstructionView {
+            program_id: &crate::ID,
+            accounts: &account_metas,
+            data: &[EscrowInstrctions::Take as u8],
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.taker,
+                self.maker,
+                self.escrow,
+                self.mint_a,
+                self.mint_b,
+                self.taker_ata_a,
+                self.taker_ata_b,
+                self.escrow_ata,
+                self.maker_ata_b,
+                self.token_program,
+                self.system_program,
+                self.associated_token_program,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Refund of an unlisted escrow signed by the maker, the vault's mint_a goes back to the
+/// maker's token account, which the program recreates from `mint_a` if it was closed.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Maker
+///   1. `[WRITE]` Escrow PDA of the maker
+///   2. `[WRITE]` Maker's mint_a token account
+///   3. `[WRITE]` Escrow vault
+///   4. `[]` Token program
+///   5. `[]` System program
+///   6. `[]` Associated token program
+///   7. `[]` Mint A
+pub struct Refund<'a> {
+    pub maker: &'a AccountView,
+    pub escrow: &'a AccountView,
+    pub maker_ata: &'a AccountView,
+    pub escrow_ata: &'a AccountView,
+    pub token_program: &'a AccountView,
+    pub system_program: &'a AccountView,
+    pub associated_token_program: &'a AccountView,
+    pub mint_a: &'a AccountView,
+}
+
+impl Refund<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas: [InstructionAccount; 8] = [
+            InstructionAccount::writable_signer(self.maker.address()),
+            InstructionAccount::writable(self.escrow.address()),
+            InstructionAccount::writable(self.maker_ata.address()),
+            InstructionAccount::writable(self.escrow_ata.address()),
+            InstructionAccount::readonly(self.token_program.address()),
+            InstructionAccount::readonly(self.system_program.address()),
+            InstructionAccount::readonly(self.associated_token_program.address()),
+            InstructionAccount::readonly(self.mint_a.address()),
+        ];
+
+        let instruction = InstructionView {
+            program_id: &crate::ID,
+            accounts: &account_metas,
+            data: &[EscrowInstrctions::Refund as u8],
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.maker,
+                self.escrow,
+                self.maker_ata,
+                self.escrow_ata,
+                self.token_program,
+                self.system_program,
+                self.associated_token_program,
+                self.mint_a,
+            ],
+            signers,
+        )
+    }
+}
@@ -0,0 +1,29 @@
+use pinocchio::error::ProgramError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowError {
+    AlreadyTaken = 0,
+    NotVesting = 1,
+    NothingToClaim = 2,
+    NoArbiter = 3,
+    NotTaken = 4,
+    NotEnoughApprovals = 5,
+    NotAnApprover = 6,
+    HashLocked = 7,
+    InvalidPreimage = 8,
+    HtlcExpired = 9,
+    HtlcNotExpired = 10,
+    UnsupportedEscrow = 11,
+    PricesDoNotCross = 12,
+    FillTooSmall = 13,
+    MarketFull = 14,
+    NotListed = 15,
+    Expired = 16,
+    NotDesignatedTaker = 17,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
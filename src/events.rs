@@ -0,0 +1,139 @@
+use pinocchio::{
+    error::ProgramError,
+    log::sol_log_data,
+    sysvars::{clock::Clock, Sysvar},
+    Address,
+};
+
+/// Bumped whenever the encoded layout of [`EscrowEvent`] changes.
+pub const EVENT_VERSION: u8 = 1;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowEventKind {
+    Make = 0,
+    Take = 1,
+    Refund = 2,
+    Match = 3,
+    Claim = 4,
+    Release = 5,
+    Reverse = 6,
+}
+
+impl TryFrom<&u8> for EscrowEventKind {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(EscrowEventKind::Make),
+            1 => Ok(EscrowEventKind::Take),
+            2 => Ok(EscrowEventKind::Refund),
+            3 => Ok(EscrowEventKind::Match),
+            4 => Ok(EscrowEventKind::Claim),
+            5 => Ok(EscrowEventKind::Release),
+            6 => Ok(EscrowEventKind::Reverse),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Lifecycle event logged with `sol_log_data`, shows up as `Program data: <base64>`.
+/// `taker` is all zeroes for Make and Refund. Match logs one event per escrow with the
+/// other maker as the taker and the filled amounts, Claim logs the claimed amount as
+/// `amount_to_give` and zero as `amount_to_receive`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EscrowEvent {
+    pub kind: EscrowEventKind,
+    pub escrow: Address,
+    pub maker: Address,
+    pub taker: Address,
+    pub mint_a: Address,
+    pub mint_b: Address,
+    pub amount_to_give: u64,
+    pub amount_to_receive: u64,
+    pub slot: u64,
+}
+
+impl EscrowEvent {
+    // -- layout --//
+    // 0 version
+    // 1 kind
+    // 2-33 escrow
+    // 34-65 maker
+    // 66-97 taker
+    // 98-129 mint_a
+    // 130-161 mint_b
+    // 162-169 amount to give
+    // 170-177 amount to receive
+    // 178-185 slot
+    pub const LEN: usize = 1 + 1 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 8;
+
+    /// Fills in the current slot from the Clock sysvar.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        kind: EscrowEventKind,
+        escrow: &Address,
+        maker: &Address,
+        taker: &Address,
+        mint_a: &Address,
+        mint_b: &Address,
+        amount_to_give: u64,
+        amount_to_receive: u64,
+    ) -> Result<Self, ProgramError> {
+        Ok(Self {
+            kind,
+            escrow: *escrow,
+            maker: *maker,
+            taker: *taker,
+            mint_a: *mint_a,
+            mint_b: *mint_b,
+            amount_to_give,
+            amount_to_receive,
+            slot: Clock::get()?.slot,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[0] = EVENT_VERSION;
+        data[1] = self.kind as u8;
+        data[2..34].copy_from_slice(self.escrow.as_ref());
+        data[34..66].copy_from_slice(self.maker.as_ref());
+        data[66..98].copy_from_slice(self.taker.as_ref());
+        data[98..130].copy_from_slice(self.mint_a.as_ref());
+        data[130..162].copy_from_slice(self.mint_b.as_ref());
+        data[162..170].copy_from_slice(&self.amount_to_give.to_le_bytes());
+        data[170..178].copy_from_slice(&self.amount_to_receive.to_le_bytes());
+        data[178..186].copy_from_slice(&self.slot.to_le_bytes());
+        data
+    }
+
+    /// Decodes an event, rejecting versions this crate doesn't know.
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN || data[0] != EVENT_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let address = |range: core::ops::Range<usize>| {
+            Address::from(<[u8; 32]>::try_from(&data[range]).unwrap())
+        };
+        let amount =
+            |range: core::ops::Range<usize>| u64::from_le_bytes(data[range].try_into().unwrap());
+
+        Ok(Self {
+            kind: EscrowEventKind::try_from(&data[1])?,
+            escrow: address(2..34),
+            maker: address(34..66),
+            taker: address(66..98),
+            mint_a: address(98..130),
+            mint_b: address(130..162),
+            amount_to_give: amount(162..170),
+            amount_to_receive: amount(170..178),
+            slot: amount(178..186),
+        })
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+}
@@ -0,0 +1,820 @@
+//! Anchor-format JSON IDL, enabled with the `idl` feature.
+//! The checked-in `idl/pinocchio_escrow.json`, and `idl/pinocchio_escrow.anchor.json` for
+//! the `anchor` feature, are compared against [`idl`] by the tests. Run them with
+//! `UPDATE_IDL=1`, with and without `--features anchor`, to rewrite both after changing
+//! an instruction.
+
+use serde_json::{json, Value};
+
+use crate::{
+    error::EscrowError,
+    instructions::EscrowInstrctions,
+    state::{AccountType, MarketEntry, DISCRIMINATOR_LEN, MARKET_CAPACITY, MAX_APPROVERS},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdlType {
+    U8,
+    U16,
+    U64,
+    I64,
+    Pubkey,
+    /// Byte string prefixed by its u32 little endian length
+    Bytes,
+    /// Fixed size byte array
+    Array(usize),
+    /// Fixed size array of another type
+    ArrayOf(&'static IdlType, usize),
+    /// Struct from the `types` section, with its encoded size
+    Defined(&'static str, usize),
+    /// Values of a type up to the end of the data, with no length prefix
+    Trailing(&'static IdlType),
+}
+
+/// Leading account discriminator, a byte unless the `anchor` feature widens it.
+const DISCRIMINATOR: IdlType = if DISCRIMINATOR_LEN == 1 {
+    IdlType::U8
+} else {
+    IdlType::Array(DISCRIMINATOR_LEN)
+};
+
+impl IdlType {
+    /// Encoded size in bytes, `None` for [`IdlType::Bytes`] and [`IdlType::Trailing`].
+    pub fn size(self) -> Option<usize> {
+        match self {
+            IdlType::U8 => Some(1),
+            IdlType::U16 => Some(2),
+            IdlType::U64 | IdlType::I64 => Some(8),
+            IdlType::Pubkey => Some(32),
+            IdlType::Bytes | IdlType::Trailing(_) => None,
+            IdlType::Array(len) => Some(len),
+            IdlType::ArrayOf(ty, len) => ty.size().map(|size| size * len),
+            IdlType::Defined(_, size) => Some(size),
+        }
+    }
+
+    fn to_json(self) -> Value {
+        match self {
+            IdlType::U8 => json!("u8"),
+            IdlType::U16 => json!("u16"),
+            IdlType::U64 => json!("u64"),
+            IdlType::I64 => json!("i64"),
+            IdlType::Pubkey => json!("pubkey"),
+            IdlType::Bytes => json!("bytes"),
+            IdlType::Array(len) => json!({ "array": ["u8", len] }),
+            IdlType::ArrayOf(ty, len) => json!({ "array": [ty.to_json(), len] }),
+            IdlType::Defined(name, _) => json!({ "defined": { "name": name } }),
+            // no Anchor equivalent, clients that can't encode it fail instead of guessing
+            IdlType::Trailing(ty) => json!({ "trailing": ty.to_json() }),
+        }
+    }
+}
+
+pub struct IdlAccount {
+    pub name: &'static str,
+    pub writable: bool,
+    pub signer: bool,
+    /// When a remaining account has to be passed
+    pub docs: &'static str,
+}
+
+pub struct IdlField {
+    pub name: &'static str,
+    pub ty: IdlType,
+    /// The data may end right before this field, leaving off every later one.
+    pub optional: bool,
+}
+
+pub struct IdlInstruction {
+    pub name: &'static str,
+    pub discriminator: u8,
+    pub docs: &'static str,
+    pub accounts: &'static [IdlAccount],
+    /// Trailing accounts after `accounts`, in order, each passed only when its docs say so
+    pub remaining_accounts: &'static [IdlAccount],
+    pub args: &'static [IdlField],
+}
+
+const fn account(name: &'static str, writable: bool, signer: bool) -> IdlAccount {
+    IdlAccount {
+        name,
+        writable,
+        signer,
+        docs: "",
+    }
+}
+
+const fn remaining(
+    name: &'static str,
+    writable: bool,
+    signer: bool,
+    docs: &'static str,
+) -> IdlAccount {
+    IdlAccount {
+        name,
+        writable,
+        signer,
+        docs,
+    }
+}
+
+const fn field(name: &'static str, ty: IdlType) -> IdlField {
+    IdlField {
+        name,
+        ty,
+        optional: false,
+    }
+}
+
+const fn optional(name: &'static str, ty: IdlType) -> IdlField {
+    IdlField {
+        name,
+        ty,
+        optional: true,
+    }
+}
+
+const OPTIONAL_DOCS: &str = "Optional, the data may end before this field and every later one";
+
+/// Arguments of make after `$leading`: the amounts, then the optional vesting, arbiter
+/// and approvals with their approvers, which Make reads as zeroes when left off.
+macro_rules! make_args {
+    ($($leading:expr),*) => {
+        &[
+            $($leading,)*
+            field("bump", IdlType::U8),
+            field("amount_to_receive", IdlType::U64),
+            field("amount_to_give", IdlType::U64),
+            optional("vesting_cliff", IdlType::U64),
+            field("vesting_duration", IdlType::U64),
+            optional("arbiter", IdlType::Pubkey),
+            optional("approval_threshold", IdlType::U8),
+            field("approvals_bump", IdlType::U8),
+            optional("approvers", IdlType::Trailing(&IdlType::Pubkey)),
+        ]
+    };
+}
+
+const MAKE_REMAINING: &[IdlAccount] = &[
+    remaining(
+        "approvals",
+        true,
+        false,
+        "Approvals PDA of the escrow, when approval_threshold is set",
+    ),
+    remaining(
+        "market",
+        true,
+        false,
+        "Market of the pair, to list the escrow in",
+    ),
+];
+
+const TAKE_REMAINING: &[IdlAccount] = &[
+    remaining(
+        "escrow_ata_b",
+        true,
+        false,
+        "Vault for mint_b, when the escrow has an arbiter",
+    ),
+    remaining(
+        "market",
+        true,
+        false,
+        "Market of the pair, when the escrow is listed",
+    ),
+    remaining(
+        "approvals",
+        true,
+        false,
+        "Approvals PDA, closed to the maker, when the escrow needs approvals",
+    ),
+    remaining(
+        "approvers",
+        false,
+        true,
+        "Any number of approvers co-signing the take, after the approvals PDA",
+    ),
+];
+
+pub const INSTRUCTIONS: &[IdlInstruction] = &[
+    IdlInstruction {
+        name: "make",
+        discriminator: EscrowInstrctions::Make as u8,
+        docs: "Locks amount_to_give of mint_a in the escrow vault for amount_to_receive of mint_b. The optional arguments add vesting, an arbiter and approvals.",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: MAKE_REMAINING,
+        args: make_args!(),
+    },
+    IdlInstruction {
+        name: "take",
+        discriminator: EscrowInstrctions::Take as u8,
+        docs: "Pays amount_to_receive of mint_b to the maker and settles mint_a to the taker.",
+        accounts: &[
+            account("taker", true, true),
+            account("maker", true, false),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("taker_ata_a", true, false),
+            account("taker_ata_b", true, false),
+            account("escrow_ata_a", true, false),
+            account("maker_ata_b", true, false),
+            account("token_program", false, false),
+            account("system_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: TAKE_REMAINING,
+        args: &[],
+    },
+    IdlInstruction {
+        name: "refund",
+        discriminator: EscrowInstrctions::Refund as u8,
+        docs: "Returns the vault to the maker. The maker or the delegate signs.",
+        accounts: &[
+            account("maker", true, false),
+            account("escrow", true, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("token_program", false, false),
+            account("system_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: &[
+            remaining("market", true, false, "Market of the pair, when the escrow is listed"),
+            remaining(
+                "approvals",
+                true,
+                false,
+                "Approvals PDA, closed to the maker, when the escrow needs approvals",
+            ),
+            remaining(
+                "delegate",
+                false,
+                true,
+                "The escrow's delegate, when it signs instead of the maker",
+            ),
+            remaining("mint_a", false, false, "When maker_ata has to be created"),
+        ],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "make_v2",
+        discriminator: EscrowInstrctions::MakeV2 as u8,
+        docs: "Make through the wincode encoded path with 64 byte amount fields.",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("bump", IdlType::U8),
+            field("amount_to_receive", IdlType::Array(64)),
+            field("amount_to_give", IdlType::Array(64)),
+        ],
+    },
+    IdlInstruction {
+        name: "take_v2",
+        discriminator: EscrowInstrctions::TakeV2 as u8,
+        docs: "Take of an escrow written by MakeV2. Take routes here on its own when the escrow is a WincodeEscrow.",
+        accounts: &[
+            account("taker", true, true),
+            account("maker", true, false),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("taker_ata_a", true, false),
+            account("taker_ata_b", true, false),
+            account("escrow_ata_a", true, false),
+            account("maker_ata_b", true, false),
+            account("token_program", false, false),
+            account("system_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "refund_v2",
+        discriminator: EscrowInstrctions::RefundV2 as u8,
+        docs: "Refund of an escrow written by MakeV2, signed by the maker. Refund routes here on its own when the escrow is a WincodeEscrow.",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "claim",
+        discriminator: EscrowInstrctions::Claim as u8,
+        docs: "Withdraws the vested part of mint_a to the taker.",
+        accounts: &[
+            account("taker", false, true),
+            account("maker", true, false),
+            account("escrow", true, false),
+            account("taker_ata_a", true, false),
+            account("escrow_ata_a", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "release",
+        discriminator: EscrowInstrctions::Release as u8,
+        docs: "Completes an arbitrated trade. Signed by the arbiter, or by both maker and taker.",
+        accounts: &[
+            account("maker", true, false),
+            account("taker", true, false),
+            account("arbiter", false, false),
+            account("escrow", true, false),
+            account("escrow_ata_a", true, false),
+            account("escrow_ata_b", true, false),
+            account("maker_ata", true, false),
+            account("taker_ata", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "reverse",
+        discriminator: EscrowInstrctions::Reverse as u8,
+        docs: "Unwinds an arbitrated trade. Signed by the arbiter, or by both maker and taker.",
+        accounts: &[
+            account("maker", true, false),
+            account("taker", true, false),
+            account("arbiter", false, false),
+            account("escrow", true, false),
+            account("escrow_ata_a", true, false),
+            account("escrow_ata_b", true, false),
+            account("maker_ata", true, false),
+            account("taker_ata", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "approve",
+        discriminator: EscrowInstrctions::Approve as u8,
+        docs: "Records an approver's sign-off on an escrow that is still open.",
+        accounts: &[
+            account("approver", false, true),
+            account("escrow", false, false),
+            account("approvals", true, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "make_htlc",
+        discriminator: EscrowInstrctions::MakeHtlc as u8,
+        docs: "Locks mint_a for the recipient behind a sha256 hashlock until the timeout.",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("bump", IdlType::U8),
+            field("amount_to_give", IdlType::U64),
+            field("hashlock", IdlType::Array(32)),
+            field("timeout", IdlType::I64),
+            field("recipient", IdlType::Pubkey),
+        ],
+    },
+    IdlInstruction {
+        name: "redeem",
+        discriminator: EscrowInstrctions::Redeem as u8,
+        docs: "Releases an HTLC to its recipient, the preimage has to hash to the escrow's hashlock.",
+        accounts: &[
+            account("maker", true, false),
+            account("escrow", false, false),
+            account("recipient_ata_a", true, false),
+            account("escrow_ata_a", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("preimage", IdlType::Bytes),
+        ],
+    },
+    IdlInstruction {
+        name: "take_many",
+        discriminator: EscrowInstrctions::TakeMany as u8,
+        docs: "Takes several plain escrows at once, one group of remaining accounts per escrow.",
+        accounts: &[
+            account("taker", true, true),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("taker_ata_a", true, false),
+            account("taker_ata_b", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[
+            remaining("escrow", true, false, "Escrow of the group, one group per escrow"),
+            remaining("vault", true, false, "Vault of the group's escrow"),
+            remaining("maker", true, false, "Maker of the group's escrow"),
+            remaining("maker_ata_b", true, false, "Mint_b account of the group's maker"),
+            remaining(
+                "market",
+                true,
+                false,
+                "Market of the pair, after the groups, when any of the escrows is listed",
+            ),
+        ],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "match",
+        discriminator: EscrowInstrctions::Match as u8,
+        docs: "Settles two crossing escrows against each other. The keeper account, when given, receives the surplus instead of maker_a.",
+        accounts: &[
+            account("escrow_a", true, false),
+            account("vault_a", true, false),
+            account("maker_a", true, false),
+            account("maker_a_ata_b", true, false),
+            account("escrow_b", true, false),
+            account("vault_b", true, false),
+            account("maker_b", true, false),
+            account("maker_b_ata_b", true, false),
+            account("token_program", false, false),
+        ],
+        remaining_accounts: &[
+            remaining("market_a", true, false, "Market of escrow_a, when it is listed"),
+            remaining("market_b", true, false, "Market of escrow_b, when it is listed"),
+            remaining(
+                "keeper_ata",
+                true,
+                false,
+                "The keeper's mint_b account of escrow_a, to collect the surplus",
+            ),
+        ],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "init_market",
+        discriminator: EscrowInstrctions::InitMarket as u8,
+        docs: "Creates the market index of a mint pair.",
+        accounts: &[
+            account("payer", true, true),
+            account("market", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("system_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("bump", IdlType::U8),
+        ],
+    },
+    IdlInstruction {
+        name: "best_offers",
+        discriminator: EscrowInstrctions::BestOffers as u8,
+        docs: "Returns the n cheapest listed offers through the return data.",
+        accounts: &[
+            account("market", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("n", IdlType::U8),
+        ],
+    },
+    IdlInstruction {
+        name: "set_delegate",
+        discriminator: EscrowInstrctions::SetDelegate as u8,
+        docs: "Sets the account allowed to refund for the maker, all zeroes removes it.",
+        accounts: &[
+            account("maker", false, true),
+            account("escrow", true, false),
+        ],
+        remaining_accounts: &[],
+        args: &[
+            field("delegate", IdlType::Pubkey),
+        ],
+    },
+    IdlInstruction {
+        name: "take_for",
+        discriminator: EscrowInstrctions::TakeFor as u8,
+        docs: "Take with mint_a settled to the leading recipient account.",
+        accounts: &[
+            account("recipient", false, false),
+            account("taker", true, true),
+            account("maker", true, false),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("taker_ata_a", true, false),
+            account("taker_ata_b", true, false),
+            account("escrow_ata_a", true, false),
+            account("maker_ata_b", true, false),
+            account("token_program", false, false),
+            account("system_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: TAKE_REMAINING,
+        args: &[],
+    },
+    IdlInstruction {
+        name: "migrate",
+        discriminator: EscrowInstrctions::Migrate as u8,
+        docs: "Converts a v1 escrow written before account discriminators into the current layout. The maker stored in the escrow signs and covers the extra rent.",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("system_program", false, false),
+        ],
+        remaining_accounts: &[],
+        args: &[],
+    },
+    IdlInstruction {
+        name: "make_with_extensions",
+        discriminator: EscrowInstrctions::MakeWithExtensions as u8,
+        docs: "Make with extension entries (u16 type, u16 length, value) stored after the escrow: expiry (1, i64), designated taker (2, pubkey) and memo (3, up to 256 bytes).",
+        accounts: &[
+            account("maker", true, true),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: MAKE_REMAINING,
+        args: make_args!(field("extensions", IdlType::Bytes)),
+    },
+    IdlInstruction {
+        name: "make_with_payer",
+        discriminator: EscrowInstrctions::MakeWithPayer as u8,
+        docs: "Make with the escrow, approvals and vault rent paid by the leading payer, so the maker can be a PDA that only signs for the mint_a transfer.",
+        accounts: &[
+            account("payer", true, true),
+            account("maker", false, true),
+            account("escrow", true, false),
+            account("mint_a", false, false),
+            account("mint_b", false, false),
+            account("maker_ata", true, false),
+            account("escrow_ata", true, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("associated_token_program", false, false),
+        ],
+        remaining_accounts: MAKE_REMAINING,
+        args: make_args!(),
+    },
+];
+
+pub const ESCROW_FIELDS: &[IdlField] = &[
+    field("discriminator", DISCRIMINATOR),
+    field("maker", IdlType::Pubkey),
+    field("mint_a", IdlType::Pubkey),
+    field("mint_b", IdlType::Pubkey),
+    field("amount_to_receive", IdlType::U64),
+    field("amount_to_give", IdlType::U64),
+    field("bump", IdlType::U8),
+    field("taker", IdlType::Pubkey),
+    field("vesting_cliff", IdlType::U64),
+    field("vesting_duration", IdlType::U64),
+    field("vesting_start", IdlType::I64),
+    field("claimed", IdlType::U64),
+    field("arbiter", IdlType::Pubkey),
+    field("approval_threshold", IdlType::U8),
+    field("hashlock", IdlType::Array(32)),
+    field("htlc_timeout", IdlType::I64),
+    field("htlc_recipient", IdlType::Pubkey),
+    field("listed", IdlType::U8),
+    field("delegate", IdlType::Pubkey),
+];
+
+pub const WINCODE_ESCROW_FIELDS: &[IdlField] = &[
+    field("discriminator", DISCRIMINATOR),
+    field("maker", IdlType::Pubkey),
+    field("mint_a", IdlType::Pubkey),
+    field("mint_b", IdlType::Pubkey),
+    field("amount_to_receive", IdlType::Array(64)),
+    field("amount_to_give", IdlType::Array(64)),
+    field("bump", IdlType::U8),
+];
+
+pub const APPROVALS_FIELDS: &[IdlField] = &[
+    field("discriminator", DISCRIMINATOR),
+    field("escrow", IdlType::Pubkey),
+    field(
+        "approvers",
+        IdlType::ArrayOf(&IdlType::Pubkey, MAX_APPROVERS),
+    ),
+    field("approver_count", IdlType::U8),
+    field("approved", IdlType::U8),
+    field("bump", IdlType::U8),
+];
+
+pub const MARKET_ENTRY_FIELDS: &[IdlField] = &[
+    field("escrow", IdlType::Pubkey),
+    field("amount_to_give", IdlType::U64),
+    field("amount_to_receive", IdlType::U64),
+];
+
+pub const MARKET_FIELDS: &[IdlField] = &[
+    field("discriminator", DISCRIMINATOR),
+    field("mint_a", IdlType::Pubkey),
+    field("mint_b", IdlType::Pubkey),
+    field("bump", IdlType::U8),
+    field("len", IdlType::U8),
+    field(
+        "entries",
+        IdlType::ArrayOf(
+            &IdlType::Defined("MarketEntry", MarketEntry::LEN),
+            MARKET_CAPACITY,
+        ),
+    ),
+];
+
+pub const ERRORS: &[(EscrowError, &str)] = &[
+    (EscrowError::AlreadyTaken, "Escrow has already been taken"),
+    (
+        EscrowError::NotVesting,
+        "Escrow is not a taken vesting escrow",
+    ),
+    (
+        EscrowError::NothingToClaim,
+        "Nothing has vested since the last claim",
+    ),
+    (EscrowError::NoArbiter, "Escrow has no arbiter"),
+    (EscrowError::NotTaken, "Escrow has not been taken"),
+    (
+        EscrowError::NotEnoughApprovals,
+        "Not enough approvers signed off",
+    ),
+    (
+        EscrowError::NotAnApprover,
+        "Signer is not an approver of the escrow",
+    ),
+    (
+        EscrowError::HashLocked,
+        "Escrow can only be redeemed with the preimage",
+    ),
+    (
+        EscrowError::InvalidPreimage,
+        "Preimage does not match the hashlock",
+    ),
+    (EscrowError::HtlcExpired, "Hashlock timeout has passed"),
+    (
+        EscrowError::HtlcNotExpired,
+        "Hashlock timeout has not passed yet",
+    ),
+    (
+        EscrowError::UnsupportedEscrow,
+        "Escrow type is not supported by this instruction",
+    ),
+    (EscrowError::PricesDoNotCross, "Escrow prices do not cross"),
+    (EscrowError::FillTooSmall, "Fill rounds down to nothing"),
+    (EscrowError::MarketFull, "Market is full of cheaper offers"),
+    (EscrowError::NotListed, "Escrow is not listed in the market"),
+    (EscrowError::Expired, "Escrow has expired"),
+    (
+        EscrowError::NotDesignatedTaker,
+        "Only the designated taker can take the escrow",
+    ),
+];
+
+fn fields_json(fields: &[IdlField]) -> Value {
+    fields
+        .iter()
+        .map(|f| {
+            let mut value = json!({ "name": f.name, "type": f.ty.to_json() });
+            if f.optional {
+                value["docs"] = json!([OPTIONAL_DOCS]);
+            }
+            value
+        })
+        .collect()
+}
+
+fn account_json(account: &IdlAccount) -> Value {
+    let mut value = json!({ "name": account.name });
+    if account.writable {
+        value["writable"] = json!(true);
+    }
+    if account.signer {
+        value["signer"] = json!(true);
+    }
+    if !account.docs.is_empty() {
+        value["docs"] = json!([account.docs]);
+    }
+    value
+}
+
+fn type_json(name: &str, fields: &[IdlField]) -> Value {
+    // Anchor clients strip the account discriminator before decoding the type
+    let fields = if cfg!(feature = "anchor") {
+        &fields[1..]
+    } else {
+        fields
+    };
+
+    struct_json(name, fields)
+}
+
+fn struct_json(name: &str, fields: &[IdlField]) -> Value {
+    json!({
+        "name": name,
+        "repr": { "kind": "c" },
+        "serialization": "bytemuck",
+        "type": { "kind": "struct", "fields": fields_json(fields) },
+    })
+}
+
+/// The tag byte, or the Anchor sighash the `anchor` feature also accepts.
+fn instruction_discriminator(tag: u8) -> Value {
+    if cfg!(feature = "anchor") {
+        let ix = EscrowInstrctions::try_from(&tag).expect("IDL instruction has a valid tag");
+        json!(ix.sighash())
+    } else {
+        json!([tag])
+    }
+}
+
+pub fn idl() -> Value {
+    let instructions: Vec<Value> = INSTRUCTIONS
+        .iter()
+        .map(|ix| {
+            let mut value = json!({
+                "name": ix.name,
+                "docs": [ix.docs],
+                "discriminator": instruction_discriminator(ix.discriminator),
+                "accounts": ix.accounts.iter().map(account_json).collect::<Vec<_>>(),
+                "args": fields_json(ix.args),
+            });
+            // not part of Anchor's spec, Anchor clients ignore it
+            if !ix.remaining_accounts.is_empty() {
+                value["remaining_accounts"] =
+                    ix.remaining_accounts.iter().map(account_json).collect();
+            }
+            value
+        })
+        .collect();
+
+    let errors: Vec<Value> = ERRORS
+        .iter()
+        .map(|(error, msg)| {
+            json!({ "code": *error as u32, "name": format!("{error:?}"), "msg": msg })
+        })
+        .collect();
+
+    json!({
+        "address": bs58::encode(crate::ID.to_bytes()).into_string(),
+        "metadata": {
+            "name": "pinocchio_escrow",
+            "version": env!("CARGO_PKG_VERSION"),
+            "spec": "0.1.0",
+        },
+        "instructions": instructions,
+        "accounts": [
+            { "name": "Escrow", "discriminator": AccountType::Escrow.discriminator() },
+            {
+                "name": "WincodeEscrow",
+                "discriminator": AccountType::WincodeEscrow.discriminator(),
+            },
+            { "name": "Approvals", "discriminator": AccountType::Approvals.discriminator() },
+            { "name": "Market", "discriminator": AccountType::Market.discriminator() },
+        ],
+        "types": [
+            type_json("Escrow", ESCROW_FIELDS),
+            type_json("WincodeEscrow", WINCODE_ESCROW_FIELDS),
+            type_json("Approvals", APPROVALS_FIELDS),
+            type_json("Market", MARKET_FIELDS),
+            struct_json("MarketEntry", MARKET_ENTRY_FIELDS),
+        ],
+        "errors": errors,
+    })
+}
@@ -0,0 +1,34 @@
+use pinocchio::{error::ProgramError, AccountView, ProgramResult};
+
+use crate::{
+    error::EscrowError,
+    state::{AccountState, Approvals, Escrow},
+};
+
+pub fn process_approve_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [approver, escrow_acc, approvals_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !approver.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Take and Refund close the approvals PDA along with the vault, so a settled escrow
+    // fails the load below; vesting and arbitrated takes also record their taker
+    if Escrow::load(escrow_acc)?.has_taker() {
+        return Err(EscrowError::AlreadyTaken.into());
+    }
+
+    let mut approvals_state = Approvals::load_mut(approvals_acc)?;
+    if approvals_state.escrow() != *escrow_acc.address() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let index = approvals_state
+        .position(approver.address())
+        .ok_or(EscrowError::NotAnApprover)?;
+    approvals_state.approve(index);
+
+    Ok(())
+}
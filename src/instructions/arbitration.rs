@@ -0,0 +1,138 @@
+use pinocchio::{cpi::Signer, error::ProgramError, AccountView, ProgramResult};
+
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    pda::escrow_signer_seeds,
+    state::{AccountState, Escrow},
+};
+
+/// Completes an arbitrated trade: the held payment goes to the maker and mint_a to the taker.
+pub fn process_release_instruction(accounts: &[AccountView]) -> ProgramResult {
+    settle_held_escrow(accounts, true)
+}
+
+/// Unwinds an arbitrated trade: the held payment goes back to the taker and mint_a to the maker.
+pub fn process_reverse_instruction(accounts: &[AccountView]) -> ProgramResult {
+    settle_held_escrow(accounts, false)
+}
+
+fn settle_held_escrow(accounts: &[AccountView], release: bool) -> ProgramResult {
+    // maker_ata / taker_ata are mint_b / mint_a on Release and mint_a / mint_b on Reverse
+    let [maker, taker, arbiter, escrow_acc, escrow_ata_a, escrow_ata_b, maker_ata, taker_ata, _token_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (amount_to_receive, amount_to_give, bump, mint_a, mint_b) = {
+        let escrow_state = Escrow::load(escrow_acc)?;
+
+        if !escrow_state.has_arbiter() {
+            return Err(EscrowError::NoArbiter.into());
+        }
+        if !escrow_state.has_taker() {
+            return Err(EscrowError::NotTaken.into());
+        }
+
+        if *maker.address() != escrow_state.maker()
+            || *taker.address() != escrow_state.taker()
+            || *arbiter.address() != escrow_state.arbiter()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the arbiter decides alone, otherwise both parties have to agree
+        if !arbiter.is_signer() && !(maker.is_signer() && taker.is_signer()) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let maker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(maker_ata)?;
+        let taker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(taker_ata)?;
+
+        if *maker_ata_state.owner() != escrow_state.maker()
+            || *taker_ata_state.owner() != escrow_state.taker()
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        let (maker_mint, taker_mint) = if release {
+            (escrow_state.mint_b(), escrow_state.mint_a())
+        } else {
+            (escrow_state.mint_a(), escrow_state.mint_b())
+        };
+        if *maker_ata_state.mint() != maker_mint || *taker_ata_state.mint() != taker_mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        (
+            escrow_state.amount_to_receive(),
+            escrow_state.amount_to_give(),
+            escrow_state.bump,
+            escrow_state.mint_a(),
+            escrow_state.mint_b(),
+        )
+    };
+
+    // Build seeds for PDA signing
+    let binding = [bump.to_le()];
+    let seed = escrow_signer_seeds(maker.address(), &binding);
+    let seeds = Signer::from(&seed);
+
+    let (payment_to, offer_to) = if release {
+        (maker_ata, taker_ata)
+    } else {
+        (taker_ata, maker_ata)
+    };
+
+    // Held payment out of the mint_b vault
+    pinocchio_token::instructions::Transfer {
+        from: escrow_ata_b,
+        to: payment_to,
+        authority: escrow_acc,
+        amount: amount_to_receive,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    // Offered mint_a out of the mint_a vault
+    pinocchio_token::instructions::Transfer {
+        from: escrow_ata_a,
+        to: offer_to,
+        authority: escrow_acc,
+        amount: amount_to_give,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    // Close both vaults, rent goes back to whoever funded them
+    pinocchio_token::instructions::CloseAccount {
+        account: escrow_ata_a,
+        destination: maker,
+        authority: escrow_acc,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    pinocchio_token::instructions::CloseAccount {
+        account: escrow_ata_b,
+        destination: taker,
+        authority: escrow_acc,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    EscrowEvent::new(
+        if release {
+            EscrowEventKind::Release
+        } else {
+            EscrowEventKind::Reverse
+        },
+        escrow_acc.address(),
+        maker.address(),
+        taker.address(),
+        &mint_a,
+        &mint_b,
+        amount_to_give,
+        amount_to_receive,
+    )?
+    .emit();
+
+    Ok(())
+}
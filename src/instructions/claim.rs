@@ -0,0 +1,101 @@
+use pinocchio::{
+    cpi::Signer,
+    error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    AccountView, ProgramResult,
+};
+
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    pda::escrow_signer_seeds,
+    state::{AccountState, Escrow},
+};
+
+pub fn process_claim_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [taker, maker, escrow_acc, taker_ata_a, escrow_ata_a, _token_program @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !taker.is_signer() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+
+    let (amount_to_claim, fully_claimed, bump, mint_a, mint_b) = {
+        let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+        let taker_ata_a_state =
+            pinocchio_token::state::TokenAccount::from_account_view(taker_ata_a)?;
+
+        if !escrow_state.is_vesting() || !escrow_state.has_taker() {
+            return Err(EscrowError::NotVesting.into());
+        }
+
+        if *taker.address() != escrow_state.taker()
+            || *taker_ata_a_state.owner() != escrow_state.taker()
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        if *taker_ata_a_state.mint() != escrow_state.mint_a()
+            || *maker.address() != escrow_state.maker()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let vested = escrow_state.vested_amount(now);
+        let amount_to_claim = vested.saturating_sub(escrow_state.claimed());
+        if amount_to_claim == 0 {
+            return Err(EscrowError::NothingToClaim.into());
+        }
+
+        escrow_state.set_claimed(vested);
+
+        (
+            amount_to_claim,
+            vested == escrow_state.amount_to_give(),
+            escrow_state.bump,
+            escrow_state.mint_a(),
+            escrow_state.mint_b(),
+        )
+    };
+
+    // Build seeds for PDA signing
+    let binding = [bump.to_le()];
+    let seed = escrow_signer_seeds(maker.address(), &binding);
+    let seeds = Signer::from(&seed);
+
+    pinocchio_token::instructions::Transfer {
+        from: escrow_ata_a,
+        to: taker_ata_a,
+        authority: escrow_acc,
+        amount: amount_to_claim,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    // Close the vault once everything is released, lamports go back to maker
+    if fully_claimed {
+        pinocchio_token::instructions::CloseAccount {
+            account: escrow_ata_a,
+            destination: maker,
+            authority: escrow_acc,
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    EscrowEvent::new(
+        EscrowEventKind::Claim,
+        escrow_acc.address(),
+        maker.address(),
+        taker.address(),
+        &mint_a,
+        &mint_b,
+        amount_to_claim,
+        0,
+    )?
+    .emit();
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use ::wincode::{SchemaRead, SchemaWrite};
+use pinocchio::error::ProgramError;
+
+use crate::instructions::{
+    htlc::MakeHtlcInstructionData, make::MakeArgs, wincode_v2, EscrowInstrctions,
+};
+
+/// Instruction data of every entry point: the [`EscrowInstrctions`] byte followed by the
+/// wincode encoded payload. Variable length tails are borrowed from the unpacked bytes
+/// and `pack` writes them back unchanged.
+#[derive(Debug, PartialEq)]
+pub enum EscrowInstruction<'a> {
+    Make(MakeArgs<'a>),
+    Take,
+    Refund,
+    MakeV2(wincode_v2::make::MakeInstructionData),
+    TakeV2,
+    RefundV2,
+    Claim,
+    Release,
+    Reverse,
+    Approve,
+    MakeHtlc(MakeHtlcInstructionData),
+    /// Preimage of the escrow's hashlock, prefixed by its u32 length
+    Redeem {
+        preimage: &'a [u8],
+    },
+    TakeMany,
+    Match,
+    InitMarket {
+        bump: u8,
+    },
+    /// Number of entries to return
+    BestOffers {
+        n: u8,
+    },
+    /// All zeroes removes the delegate
+    SetDelegate {
+        delegate: [u8; 32],
+    },
+    TakeFor,
+    Migrate,
+    /// Extension entries, see [`crate::state::ExtensionType`], then a regular Make
+    MakeWithExtensions {
+        extensions: &'a [u8],
+        make: MakeArgs<'a>,
+    },
+    /// Make with the rent paid by a leading payer account
+    MakeWithPayer(MakeArgs<'a>),
+}
+
+impl<'a> EscrowInstruction<'a> {
+    pub fn unpack(instruction_data: &'a [u8]) -> Result<Self, ProgramError> {
+        let (descriminator, data) = split_descriminator(instruction_data)?;
+
+        let (instruction, rest) = match descriminator {
+            EscrowInstrctions::Make => (Self::Make(MakeArgs::unpack(data)?), &[][..]),
+            EscrowInstrctions::Take => (Self::Take, data),
+            EscrowInstrctions::Refund => (Self::Refund, data),
+            EscrowInstrctions::MakeV2 => {
+                let (ix_data, rest) =
+                    read_payload(data, wincode_v2::make::MakeInstructionData::LEN)?;
+                (Self::MakeV2(ix_data), rest)
+            }
+            EscrowInstrctions::TakeV2 => (Self::TakeV2, data),
+            EscrowInstrctions::RefundV2 => (Self::RefundV2, data),
+            EscrowInstrctions::Claim => (Self::Claim, data),
+            EscrowInstrctions::Release => (Self::Release, data),
+            EscrowInstrctions::Reverse => (Self::Reverse, data),
+            EscrowInstrctions::Approve => (Self::Approve, data),
+            EscrowInstrctions::MakeHtlc => {
+                let (ix_data, rest) = read_payload(data, MakeHtlcInstructionData::LEN)?;
+                (Self::MakeHtlc(ix_data), rest)
+            }
+            EscrowInstrctions::Redeem => {
+                let (len, data) = read_payload::<u32>(data, 4)?;
+                let (preimage, rest) = data
+                    .split_at_checked(len as usize)
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                (Self::Redeem { preimage }, rest)
+            }
+            EscrowInstrctions::TakeMany => (Self::TakeMany, data),
+            EscrowInstrctions::Match => (Self::Match, data),
+            EscrowInstrctions::InitMarket => {
+                let (bump, rest) = read_payload(data, 1)?;
+                (Self::InitMarket { bump }, rest)
+            }
+            EscrowInstrctions::BestOffers => {
+                let (n, rest) = read_payload(data, 1)?;
+                (Self::BestOffers { n }, rest)
+            }
+            EscrowInstrctions::SetDelegate => {
+                let (delegate, rest) = read_payload(data, 32)?;
+                (Self::SetDelegate { delegate }, rest)
+            }
+            EscrowInstrctions::TakeFor => (Self::TakeFor, data),
+            EscrowInstrctions::Migrate => (Self::Migrate, data),
+            EscrowInstrctions::MakeWithExtensions => {
+                // -- layout --//
+                // 0-3 length of the extension entries
+                // 4.. extension entries, then the Make instruction data
+                let (len, data) = read_payload::<u32>(data, 4)?;
+                let (extensions, data) = data
+                    .split_at_checked(len as usize)
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                let make = MakeArgs::unpack(data)?;
+                (Self::MakeWithExtensions { extensions, make }, &[][..])
+            }
+            EscrowInstrctions::MakeWithPayer => {
+                (Self::MakeWithPayer(MakeArgs::unpack(data)?), &[][..])
+            }
+        };
+
+        // trailing bytes mean the client and the program disagree on the layout
+        if !rest.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(instruction)
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = vec![self.discriminator() as u8];
+        match self {
+            Self::Make(make) | Self::MakeWithPayer(make) => make.pack_into(&mut data),
+            Self::MakeV2(ix_data) => write_payload(&mut data, ix_data),
+            Self::MakeHtlc(ix_data) => write_payload(&mut data, ix_data),
+            Self::Redeem { preimage } => {
+                write_payload(&mut data, &(preimage.len() as u32));
+                data.extend_from_slice(preimage);
+            }
+            Self::InitMarket { bump } => write_payload(&mut data, bump),
+            Self::BestOffers { n } => write_payload(&mut data, n),
+            Self::SetDelegate { delegate } => write_payload(&mut data, delegate),
+            Self::MakeWithExtensions { extensions, make } => {
+                write_payload(&mut data, &(extensions.len() as u32));
+                data.extend_from_slice(extensions);
+                make.pack_into(&mut data);
+            }
+            Self::Take
+            | Self::Refund
+            | Self::TakeV2
+            | Self::RefundV2
+            | Self::Claim
+            | Self::Release
+            | Self::Reverse
+            | Self::Approve
+            | Self::TakeMany
+            | Self::Match
+            | Self::TakeFor
+            | Self::Migrate => {}
+        }
+        data
+    }
+
+    /// [`EscrowInstruction::pack`] with the Anchor sighash in place of the tag byte.
+    #[cfg(feature = "anchor")]
+    pub fn pack_anchor(&self) -> Vec<u8> {
+        let mut data = self.discriminator().sighash().to_vec();
+        data.extend_from_slice(&self.pack()[1..]);
+        data
+    }
+
+    pub fn discriminator(&self) -> EscrowInstrctions {
+        match self {
+            Self::Make(_) => EscrowInstrctions::Make,
+            Self::Take => EscrowInstrctions::Take,
+            Self::Refund => EscrowInstrctions::Refund,
+            Self::MakeV2(_) => EscrowInstrctions::MakeV2,
+            Self::TakeV2 => EscrowInstrctions::TakeV2,
+            Self::RefundV2 => EscrowInstrctions::RefundV2,
+            Self::Claim => EscrowInstrctions::Claim,
+            Self::Release => EscrowInstrctions::Release,
+            Self::Reverse => EscrowInstrctions::Reverse,
+            Self::Approve => EscrowInstrctions::Approve,
+            Self::MakeHtlc(_) => EscrowInstrctions::MakeHtlc,
+            Self::Redeem { .. } => EscrowInstrctions::Redeem,
+            Self::TakeMany => EscrowInstrctions::TakeMany,
+            Self::Match => EscrowInstrctions::Match,
+            Self::InitMarket { .. } => EscrowInstrctions::InitMarket,
+            Self::BestOffers { .. } => EscrowInstrctions::BestOffers,
+            Self::SetDelegate { .. } => EscrowInstrctions::SetDelegate,
+            Self::TakeFor => EscrowInstrctions::TakeFor,
+            Self::Migrate => EscrowInstrctions::Migrate,
+            Self::MakeWithExtensions { .. } => EscrowInstrctions::MakeWithExtensions,
+            Self::MakeWithPayer(_) => EscrowInstrctions::MakeWithPayer,
+        }
+    }
+}
+
+/// The tag byte, or with the `anchor` feature an Anchor sighash, which is tried first.
+/// Two sighashes start with a valid tag. Refund's starts with its own tag and is longer
+/// than a tagged Refund. TakeMany's starts with 16, the SetDelegate tag, so a tagged
+/// SetDelegate whose delegate begins with the other seven sighash bytes reads as a
+/// TakeMany with trailing bytes, which `unpack` rejects rather than misreads. Such a
+/// delegate has to be set through the SetDelegate sighash.
+fn split_descriminator(
+    instruction_data: &[u8],
+) -> Result<(EscrowInstrctions, &[u8]), ProgramError> {
+    #[cfg(feature = "anchor")]
+    if let Some(split) = EscrowInstrctions::from_sighash(instruction_data) {
+        return Ok(split);
+    }
+
+    let (descriminator, data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok((EscrowInstrctions::try_from(descriminator)?, data))
+}
+
+/// Decodes the fixed `len` byte payload at the start of `data`, returns it and the rest.
+pub(crate) fn read_payload<'a, T>(data: &'a [u8], len: usize) -> Result<(T, &'a [u8]), ProgramError>
+where
+    T: SchemaRead<'a, Dst = T>,
+{
+    let (payload, rest) = data
+        .split_at_checked(len)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let payload =
+        ::wincode::deserialize::<T>(payload).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    Ok((payload, rest))
+}
+
+pub(crate) fn write_payload<T>(data: &mut Vec<u8>, payload: &T)
+where
+    T: SchemaWrite<Src = T>,
+{
+    data.extend_from_slice(&::wincode::serialize(payload).unwrap());
+}
+
+/// Encodes `payload` at the start of `dst`, for callers building instruction data on the
+/// stack.
+pub(crate) fn write_payload_into<T>(dst: &mut [u8], payload: &T) -> Result<(), ProgramError>
+where
+    T: SchemaWrite<Src = T>,
+{
+    ::wincode::serialize_into(&mut &mut dst[..], payload)
+        .map_err(|_| ProgramError::InvalidInstructionData)
+}
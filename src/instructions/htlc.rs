@@ -0,0 +1,206 @@
+use ::wincode::{SchemaRead, SchemaWrite};
+use pinocchio::{
+    cpi::Signer,
+    error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    AccountView, Address, ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    pda::{create_escrow_address_with_bump, escrow_signer_seeds},
+    state::{AccountState, Escrow},
+};
+
+// -- layout --//
+// 0 bump
+// 1-8 amount to give
+// 9-40 sha256 hash of the preimage
+// 41-48 timeout as unix timestamp
+// 49-80 recipient of mint_a
+#[derive(Clone, Copy, Debug, PartialEq, SchemaRead, SchemaWrite)]
+pub struct MakeHtlcInstructionData {
+    pub bump: u8,
+    pub amount_to_give: u64,
+    pub hashlock: [u8; 32],
+    pub timeout: i64,
+    pub recipient: [u8; 32],
+}
+
+impl MakeHtlcInstructionData {
+    pub const LEN: usize = 1 + 8 + 32 + 8 + 32;
+}
+
+pub fn process_make_htlc_instruction(
+    accounts: &[AccountView],
+    ix_data: MakeHtlcInstructionData,
+) -> ProgramResult {
+    let [maker, escrow_acc, mint_a, maker_ata, escrow_ata, system_program, token_program, _assoociated_token_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !maker.is_signer() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    let MakeHtlcInstructionData {
+        bump,
+        amount_to_give,
+        hashlock,
+        timeout,
+        recipient,
+    } = ix_data;
+
+    if hashlock == [0u8; 32] || timeout <= Clock::get()?.unix_timestamp {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    {
+        let maker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(maker_ata)?;
+        if maker_ata_state.owner() != maker.address() {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if maker_ata_state.mint() != mint_a.address() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    if create_escrow_address_with_bump(maker.address(), bump) != *escrow_acc.address() {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump_seed = [bump];
+    let signer_seeds = escrow_signer_seeds(maker.address(), &bump_seed);
+
+    CreateAccount {
+        from: maker,
+        to: escrow_acc,
+        lamports: Rent::get()?.try_minimum_balance(Escrow::LEN)?,
+        space: Escrow::LEN as u64,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&[Signer::from(&signer_seeds)])?;
+
+    {
+        let mut escrow_state = Escrow::init(escrow_acc)?;
+
+        escrow_state.set_maker(maker.address());
+        escrow_state.set_mint_a(mint_a.address());
+        escrow_state.set_amount_to_give(amount_to_give);
+        escrow_state.bump = bump;
+        escrow_state.set_hashlock(&hashlock);
+        escrow_state.set_htlc_timeout(timeout);
+        escrow_state.set_htlc_recipient(&recipient.into());
+    }
+
+    pinocchio_associated_token_account::instructions::CreateIdempotent {
+        funding_account: maker,
+        account: escrow_ata,
+        wallet: escrow_acc,
+        mint: mint_a,
+        system_program,
+        token_program,
+    }
+    .invoke()?;
+
+    pinocchio_token::instructions::Transfer {
+        from: maker_ata,
+        to: escrow_ata,
+        authority: maker,
+        amount: amount_to_give,
+    }
+    .invoke()?;
+
+    // HTLCs have no mint_b leg
+    EscrowEvent::new(
+        EscrowEventKind::Make,
+        escrow_acc.address(),
+        maker.address(),
+        &Address::default(),
+        mint_a.address(),
+        &Address::default(),
+        amount_to_give,
+        0,
+    )?
+    .emit();
+
+    Ok(())
+}
+
+/// Anyone holding the preimage can release the locked mint_a to the recipient before the timeout.
+pub fn process_redeem_instruction(accounts: &[AccountView], preimage: &[u8]) -> ProgramResult {
+    let [maker, escrow_acc, recipient_ata_a, escrow_ata_a, _token_program @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (amount_to_give, bump, mint_a, recipient) = {
+        let escrow_state = Escrow::load(escrow_acc)?;
+
+        if !escrow_state.is_htlc() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if Clock::get()?.unix_timestamp >= escrow_state.htlc_timeout() {
+            return Err(EscrowError::HtlcExpired.into());
+        }
+        if solana_sha256_hasher::hash(preimage).to_bytes() != escrow_state.hashlock() {
+            return Err(EscrowError::InvalidPreimage.into());
+        }
+
+        let recipient_ata_a_state =
+            pinocchio_token::state::TokenAccount::from_account_view(recipient_ata_a)?;
+        if *recipient_ata_a_state.owner() != escrow_state.htlc_recipient() {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if *recipient_ata_a_state.mint() != escrow_state.mint_a()
+            || *maker.address() != escrow_state.maker()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        (
+            escrow_state.amount_to_give(),
+            escrow_state.bump,
+            escrow_state.mint_a(),
+            escrow_state.htlc_recipient(),
+        )
+    };
+
+    // Build seeds for PDA signing
+    let binding = [bump.to_le()];
+    let seed = escrow_signer_seeds(maker.address(), &binding);
+    let seeds = Signer::from(&seed);
+
+    pinocchio_token::instructions::Transfer {
+        from: escrow_ata_a,
+        to: recipient_ata_a,
+        authority: escrow_acc,
+        amount: amount_to_give,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    // Close the vault token account, lamports go back to maker
+    pinocchio_token::instructions::CloseAccount {
+        account: escrow_ata_a,
+        destination: maker,
+        authority: escrow_acc,
+    }
+    .invoke_signed(&[seeds.clone()])?;
+
+    EscrowEvent::new(
+        EscrowEventKind::Take,
+        escrow_acc.address(),
+        maker.address(),
+        &recipient,
+        &mint_a,
+        &Address::default(),
+        amount_to_give,
+        0,
+    )?
+    .emit();
+
+    Ok(())
+}
@@ -1,25 +1,177 @@
+use ::wincode::{SchemaRead, SchemaWrite};
 use pinocchio::{
     cpi::{Seed, Signer},
     error::ProgramError,
-    sysvars::{rent::Rent, Sysvar},
-    AccountView, ProgramResult,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    AccountView, Address, ProgramResult,
 };
 use pinocchio_pubkey::derive_address;
 use pinocchio_system::instructions::CreateAccount;
 
-use crate::state::Escrow;
+use crate::{
+    events::{EscrowEvent, EscrowEventKind},
+    instructions::{
+        codec::{read_payload, write_payload},
+        market::list_escrow,
+    },
+    pda::{create_escrow_address_with_bump, escrow_signer_seeds},
+    state::{
+        expiry, get_extension, validate_extensions, AccountState, Approvals, Escrow, ExtensionType,
+        MAX_APPROVERS,
+    },
+};
+
+// -- layout --//
+// descriminator is stripped at the entry point's process instruction
+// 0 - bump
+// 1-8 amount to recive
+// 9-16 amount to give
+// 17-24 vesting cliff in seconds (optional)
+// 25-32 vesting duration in seconds (optional)
+// 33-64 arbiter (optional, needs the vesting fields zeroed before it)
+// 65 approval threshold (optional, needs the fields before it)
+// 66 approvals PDA bump
+// 67.. approver pubkeys, 32 bytes each
+//
+#[derive(Clone, Copy, Debug, PartialEq, SchemaRead, SchemaWrite)]
+pub struct MakeInstructionData {
+    pub bump: u8,
+    pub amount_to_receive: u64,
+    pub amount_to_give: u64,
+}
+
+impl MakeInstructionData {
+    pub const LEN: usize = 1 + 8 + 8;
+}
+
+/// Optional tail of the Make payload, a part left out reads as zeroes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, SchemaRead, SchemaWrite)]
+pub struct MakeOptions {
+    pub vesting_cliff: u64,
+    pub vesting_duration: u64,
+    pub arbiter: [u8; 32],
+    pub approval_threshold: u8,
+    pub approvals_bump: u8,
+}
+
+impl MakeOptions {
+    pub const LEN: usize = 8 + 8 + 32 + 1 + 1;
+
+    /// Where the tail may end when it stops before the approvers.
+    const PARTIAL_LENS: [usize; 3] = [0, 16, 48];
+}
+
+/// Decoded Make payload. `options` is `None` when the data stops after the amounts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MakeArgs<'a> {
+    pub data: MakeInstructionData,
+    pub options: Option<MakeOptions>,
+    /// Approver pubkeys, 32 bytes each
+    pub approvers: &'a [u8],
+}
+
+impl<'a> MakeArgs<'a> {
+    /// The options have to stop on a field boundary, and approvers are only accepted
+    /// with a threshold.
+    pub fn unpack(data: &'a [u8]) -> Result<Self, ProgramError> {
+        let (ix_data, tail) = read_payload(data, MakeInstructionData::LEN)?;
+        if tail.is_empty() {
+            return Ok(Self {
+                data: ix_data,
+                options: None,
+                approvers: &[],
+            });
+        }
+
+        let (options, approvers) = tail.split_at(tail.len().min(MakeOptions::LEN));
+        if options.len() < MakeOptions::LEN && !MakeOptions::PARTIAL_LENS.contains(&options.len()) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut padded = [0u8; MakeOptions::LEN];
+        padded[..options.len()].copy_from_slice(options);
+        let (options, _) = read_payload::<MakeOptions>(&padded, MakeOptions::LEN)?;
+
+        if options.approval_threshold == 0 && !approvers.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            data: ix_data,
+            options: Some(options),
+            approvers,
+        })
+    }
+
+    /// Writes the amounts, then the whole options and the approvers when there are options.
+    pub fn pack_into(&self, data: &mut Vec<u8>) {
+        write_payload(data, &self.data);
+        if let Some(options) = &self.options {
+            write_payload(data, options);
+            data.extend_from_slice(self.approvers);
+        }
+    }
+}
+
+pub fn process_make_instruction(accounts: &[AccountView], args: MakeArgs) -> ProgramResult {
+    make(accounts, args, &[], None)
+}
+
+/// Make with the escrow, approvals and vault rent paid by the leading `payer` account.
+/// `maker` then only has to sign for the mint_a transfer, so it can be a PDA signing
+/// through the calling program's seeds.
+pub fn process_make_with_payer_instruction(
+    accounts: &[AccountView],
+    args: MakeArgs,
+) -> ProgramResult {
+    let [payer, accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    make(accounts, args, &[], Some(payer))
+}
 
-pub fn process_make_instruction(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+/// Make with extension entries stored after the escrow, see [`crate::state::ExtensionType`].
+pub fn process_make_with_extensions_instruction(
+    accounts: &[AccountView],
+    extensions: &[u8],
+    args: MakeArgs,
+) -> ProgramResult {
+    validate_extensions(extensions)?;
+
+    if let Some(value) = get_extension(extensions, ExtensionType::Expiry)? {
+        if expiry(value) <= Clock::get()?.unix_timestamp {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    make(accounts, args, extensions, None)
+}
+
+fn make(
+    accounts: &[AccountView],
+    args: MakeArgs,
+    extensions: &[u8],
+    payer: Option<&AccountView>,
+) -> ProgramResult {
     let [maker, escrow_acc, mint_a, mint_b, maker_ata, escrow_ata, system_program, token_program, _assoociated_token_program @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    // the maker's signature is no longer implied by paying for the escrow account
+    if !maker.is_signer() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+    let payer = payer.unwrap_or(maker);
+
+    let ix_data = args.data;
+    let options = args.options.unwrap_or_default();
+    let approvers = args.approvers;
+
     // PDA Derivation
-    let bump = data[0];
-    let seed = [b"escrow".as_ref(), maker.address().as_ref(), &[bump]];
-    let escrow_acc_pda = derive_address(&seed, None, &crate::ID.to_bytes());
+    let bump = ix_data.bump;
+    let escrow_acc_pda = create_escrow_address_with_bump(maker.address(), bump);
 
     // Account validation
     {
@@ -30,55 +182,133 @@ pub fn process_make_instruction(accounts: &[AccountView], data: &[u8]) -> Progra
         if maker_ata_state.mint() != mint_a.address() {
             return Err(ProgramError::InvalidAccountData);
         }
-        assert_eq!(escrow_acc_pda, *escrow_acc.address().as_array());
     }
-    // -- layout --//
-    // 0 - descriminator but stripped at the entry point's process instruction
-    // 1 - bump
-    // 1-8 amount to recive
-    // 9-16 amount to give
-    //
+    if escrow_acc_pda != *escrow_acc.address() {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let amount_to_receive = ix_data.amount_to_receive;
+    let amount_to_give = ix_data.amount_to_give;
+
+    // without the vesting tail the escrow settles in full on Take
+    let vesting_cliff = options.vesting_cliff;
+    let vesting_duration = options.vesting_duration;
+    if vesting_cliff > vesting_duration {
+        return Err(ProgramError::InvalidInstructionData);
+    }
 
-    let amount_to_receive = unsafe { *(data.as_ptr().add(1) as *const u64) }; // here we are starting from first byte because first byte is actually the bump
-    let amount_to_give = unsafe { *(data.as_ptr().add(9) as *const u64) };
+    // an arbiter holds the payment back, which a vesting Take can't do
+    let arbiter = options.arbiter;
+    if vesting_duration > 0 && arbiter != [0u8; 32] {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // M-of-N approvers have to sign off before Take can complete
+    let approval_threshold = options.approval_threshold;
+    if approval_threshold > 0 {
+        let approver_count = approvers.len() / 32;
+        if approvers.len() % 32 != 0
+            || approver_count > MAX_APPROVERS
+            || approval_threshold as usize > approver_count
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        for (i, approver) in approvers.chunks_exact(32).enumerate() {
+            if approvers
+                .chunks_exact(32)
+                .skip(i + 1)
+                .any(|a| a == approver)
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+    }
 
     let bump = [bump.to_le()];
-    let seed = [
-        Seed::from(b"escrow"),
-        Seed::from(maker.address().as_array()),
-        Seed::from(&bump),
-    ];
+    let seed = escrow_signer_seeds(maker.address(), &bump);
     let seeds = Signer::from(&seed);
 
     // state change
     unsafe {
         if escrow_acc.owner() != &crate::ID {
             CreateAccount {
-                from: maker,
+                from: payer,
                 to: escrow_acc,
-                lamports: Rent::get()?.try_minimum_balance(Escrow::LEN)?,
-                space: Escrow::LEN as u64,
+                lamports: Rent::get()?.try_minimum_balance(Escrow::LEN + extensions.len())?,
+                space: (Escrow::LEN + extensions.len()) as u64,
                 owner: &crate::ID,
             }
             .invoke_signed(&[seeds.clone()])?;
 
             {
-                let escrow_state = Escrow::from_account_info(escrow_acc)?;
+                let mut escrow_state = Escrow::init(escrow_acc)?;
 
                 escrow_state.set_maker(maker.address());
                 escrow_state.set_mint_a(mint_a.address());
                 escrow_state.set_mint_b(mint_b.address());
                 escrow_state.set_amount_to_receive(amount_to_receive);
                 escrow_state.set_amount_to_give(amount_to_give);
-                escrow_state.bump = data[0];
+                escrow_state.bump = ix_data.bump;
+                escrow_state.set_vesting_cliff(vesting_cliff);
+                escrow_state.set_vesting_duration(vesting_duration);
+                escrow_state.set_arbiter(&arbiter.into());
+                escrow_state.approval_threshold = approval_threshold;
+                escrow_state.extensions_mut().copy_from_slice(extensions);
             }
         } else {
             return Err(ProgramError::IllegalOwner);
         }
     }
 
-    pinocchio_associated_token_account::instructions::Create {
-        funding_account: maker,
+    if approval_threshold > 0 {
+        let approvals_acc = accounts.get(9).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        let approvals_bump = [options.approvals_bump];
+        let approvals_pda = derive_address(
+            &[
+                b"approvals".as_ref(),
+                escrow_acc.address().as_ref(),
+                &approvals_bump,
+            ],
+            None,
+            &crate::ID.to_bytes(),
+        );
+        if approvals_pda != *approvals_acc.address().as_array() {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        let approvals_seed = [
+            Seed::from(b"approvals"),
+            Seed::from(escrow_acc.address().as_array()),
+            Seed::from(&approvals_bump),
+        ];
+
+        CreateAccount {
+            from: payer,
+            to: approvals_acc,
+            lamports: Rent::get()?.try_minimum_balance(Approvals::LEN)?,
+            space: Approvals::LEN as u64,
+            owner: &crate::ID,
+        }
+        .invoke_signed(&[Signer::from(&approvals_seed)])?;
+
+        let mut approvals_state = Approvals::init(approvals_acc)?;
+        approvals_state.set_escrow(escrow_acc.address());
+        for (i, approver) in approvers.chunks_exact(32).enumerate() {
+            let approver: [u8; 32] = approver.try_into().unwrap();
+            approvals_state.set_approver(i, &approver.into());
+        }
+        approvals_state.approver_count = (approvers.len() / 32) as u8;
+        approvals_state.bump = options.approvals_bump;
+    }
+
+    // Optional trailing market of the pair, after the approvals PDA when there is one
+    let market_index = if approval_threshold > 0 { 10 } else { 9 };
+    if let Some(market_acc) = accounts.get(market_index) {
+        list_escrow(market_acc, escrow_acc)?;
+    }
+
+    pinocchio_associated_token_account::instructions::CreateIdempotent {
+        funding_account: payer,
         account: escrow_ata,
         wallet: escrow_acc,
         mint: mint_a,
@@ -95,5 +325,17 @@ pub fn process_make_instruction(accounts: &[AccountView], data: &[u8]) -> Progra
     }
     .invoke()?;
 
+    EscrowEvent::new(
+        EscrowEventKind::Make,
+        escrow_acc.address(),
+        maker.address(),
+        &Address::default(),
+        mint_a.address(),
+        mint_b.address(),
+        amount_to_give,
+        amount_to_receive,
+    )?
+    .emit();
+
     Ok(())
 }
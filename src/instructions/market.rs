@@ -0,0 +1,134 @@
+use pinocchio::{
+    cpi::{set_return_data, Seed, Signer},
+    error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    AccountView, ProgramResult,
+};
+use pinocchio_pubkey::derive_address;
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+use crate::pda::find_market_address;
+use crate::{
+    pda::MARKET_SEED,
+    state::{AccountRefMut, AccountState, Escrow, Market, MarketEntry},
+};
+
+/// Most entries that fit in the 1024 byte return data
+pub const MAX_BEST_OFFERS: usize = 1024 / MarketEntry::LEN;
+
+pub fn process_init_market_instruction(accounts: &[AccountView], bump: u8) -> ProgramResult {
+    let [payer, market_acc, mint_a, mint_b, _system_program @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer.is_signer() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    let seed = [
+        MARKET_SEED,
+        mint_a.address().as_ref(),
+        mint_b.address().as_ref(),
+        &[bump],
+    ];
+    let market_pda = derive_address(&seed, None, &crate::ID.to_bytes());
+    if market_pda != *market_acc.address().as_array() {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // any other bump would open a second market for the pair, the bump search
+    // only exists on-chain, where this processor runs
+    #[cfg(target_os = "solana")]
+    if find_market_address(mint_a.address(), mint_b.address()).1 != bump {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump_seed = [bump];
+    let signer_seeds = [
+        Seed::from(MARKET_SEED),
+        Seed::from(mint_a.address().as_array()),
+        Seed::from(mint_b.address().as_array()),
+        Seed::from(&bump_seed),
+    ];
+
+    CreateAccount {
+        from: payer,
+        to: market_acc,
+        lamports: Rent::get()?.try_minimum_balance(Market::LEN)?,
+        space: Market::LEN as u64,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&[Signer::from(&signer_seeds)])?;
+
+    let mut market_state = Market::init(market_acc)?;
+    market_state.set_mint_a(mint_a.address());
+    market_state.set_mint_b(mint_b.address());
+    market_state.bump = bump;
+
+    Ok(())
+}
+
+/// Read-only: returns the `n` cheapest entries through the return data,
+/// each one as escrow (32) | amount to give (8) | amount to receive (8).
+pub fn process_best_offers_instruction(accounts: &[AccountView], n: u8) -> ProgramResult {
+    let [market_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let market_state = Market::load(market_acc)?;
+    let entries = market_state.entries();
+    let entries = &entries[..(n as usize).min(entries.len()).min(MAX_BEST_OFFERS)];
+
+    let mut offers = [0u8; MAX_BEST_OFFERS * MarketEntry::LEN];
+    for (chunk, entry) in offers.chunks_exact_mut(MarketEntry::LEN).zip(entries) {
+        chunk.copy_from_slice(entry.as_bytes());
+    }
+    set_return_data(&offers[..entries.len() * MarketEntry::LEN]);
+
+    Ok(())
+}
+
+fn checked_market<'a>(
+    market_acc: &'a AccountView,
+    escrow_state: &Escrow,
+) -> Result<AccountRefMut<'a, Market>, ProgramError> {
+    let market_state = Market::load_mut(market_acc)?;
+    if market_state.mint_a() != escrow_state.mint_a()
+        || market_state.mint_b() != escrow_state.mint_b()
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(market_state)
+}
+
+/// Adds a freshly made escrow to the market of its pair.
+pub fn list_escrow(market_acc: &AccountView, escrow_acc: &AccountView) -> ProgramResult {
+    let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+    let mut market_state = checked_market(market_acc, &escrow_state)?;
+
+    // an evicted escrow keeps its listed flag, delist_escrow lets it go without an entry
+    market_state.insert(MarketEntry::new(
+        escrow_acc.address(),
+        escrow_state.amount_to_give(),
+        escrow_state.amount_to_receive(),
+    ))?;
+    escrow_state.listed = 1;
+
+    Ok(())
+}
+
+/// Drops a listed escrow from its market once it is taken or refunded.
+pub fn delist_escrow(market_acc: &AccountView, escrow_acc: &AccountView) -> ProgramResult {
+    let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+    let mut market_state = checked_market(market_acc, &escrow_state)?;
+
+    // escrows evicted by a cheaper listing are already out of the slab
+    if market_state.contains(escrow_acc.address()) {
+        market_state.remove(escrow_acc.address())?;
+    }
+    escrow_state.listed = 0;
+
+    Ok(())
+}
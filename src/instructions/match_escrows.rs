@@ -0,0 +1,261 @@
+use pinocchio::{cpi::Signer, error::ProgramError, AccountView, ProgramResult};
+
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    instructions::{
+        market::{delist_escrow, list_escrow},
+        take::check_take_extensions,
+    },
+    pda::escrow_signer_seeds,
+    state::{AccountState, Escrow},
+};
+
+/// Settles two opposing escrows against each other, no taker involved.
+/// `A` gives X for Y and `B` gives Y for X. The fill is limited by the smaller side
+/// and whatever Y `B` gives beyond what `A` asked for is the surplus, paid to the keeper's
+/// optional mint Y account or otherwise to `A`.
+pub fn process_match_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [escrow_a, vault_a, maker_a, maker_a_ata_b, escrow_b, vault_b, maker_b, maker_b_ata_b, _token_program, remaining @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if escrow_a.address() == escrow_b.address() {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let (give_a, receive_a, bump_a, listed_a, give_b, receive_b, bump_b, listed_b, mint_x, mint_y) = {
+        let state_a = Escrow::load(escrow_a)?;
+        let state_b = Escrow::load(escrow_b)?;
+
+        if !state_a.is_plain() || !state_b.is_plain() {
+            return Err(EscrowError::UnsupportedEscrow.into());
+        }
+
+        if state_a.mint_a() != state_b.mint_b() || state_a.mint_b() != state_b.mint_a() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let maker_a_ata_b_state =
+            pinocchio_token::state::TokenAccount::from_account_view(maker_a_ata_b)?;
+        let maker_b_ata_b_state =
+            pinocchio_token::state::TokenAccount::from_account_view(maker_b_ata_b)?;
+
+        if *maker_a.address() != state_a.maker()
+            || *maker_b.address() != state_b.maker()
+            || *maker_a_ata_b_state.owner() != state_a.maker()
+            || *maker_b_ata_b_state.owner() != state_b.maker()
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        if *maker_a_ata_b_state.mint() != state_a.mint_b()
+            || *maker_b_ata_b_state.mint() != state_b.mint_b()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        (
+            state_a.amount_to_give(),
+            state_a.amount_to_receive(),
+            state_a.bump,
+            state_a.is_listed(),
+            state_b.amount_to_give(),
+            state_b.amount_to_receive(),
+            state_b.bump,
+            state_b.is_listed(),
+            state_a.mint_a(),
+            state_a.mint_b(),
+        )
+    };
+
+    // Optional trailing accounts, in order: the market of A when A is listed, the market
+    // of B when B is listed, then the keeper's mint Y account
+    let mut remaining = remaining;
+    let market_a = split_if(&mut remaining, listed_a)?;
+    let market_b = split_if(&mut remaining, listed_b)?;
+    let keeper_ata = remaining.first();
+
+    if let Some(keeper_ata) = keeper_ata {
+        let keeper_ata_state = pinocchio_token::state::TokenAccount::from_account_view(keeper_ata)?;
+        if *keeper_ata_state.mint() != mint_y {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // each maker takes the other's escrow
+    check_take_extensions(escrow_a, maker_b.address())?;
+    check_take_extensions(escrow_b, maker_a.address())?;
+
+    // X moving from A's vault to B's maker
+    let fill_x = give_a.min(receive_b);
+    if fill_x == 0 {
+        return Err(EscrowError::FillTooSmall.into());
+    }
+
+    // Y owed to A at A's price, rounded up in A's favour
+    let owed_to_a = if fill_x == give_a {
+        receive_a
+    } else {
+        (fill_x as u128 * receive_a as u128).div_ceil(give_a as u128) as u64
+    };
+
+    // Y that B pays at B's price, rounded down in B's favour
+    let paid_by_b = if fill_x == receive_b {
+        give_b
+    } else {
+        (fill_x as u128 * give_b as u128 / receive_b as u128) as u64
+    };
+
+    if paid_by_b < owed_to_a {
+        return Err(EscrowError::PricesDoNotCross.into());
+    }
+    let surplus = paid_by_b - owed_to_a;
+
+    let remaining_give_a = give_a - fill_x;
+    let remaining_receive_a = receive_a - owed_to_a;
+    // a partially filled A must still ask for something, or a Take would get it for free
+    if remaining_give_a > 0 && remaining_receive_a == 0 {
+        return Err(EscrowError::FillTooSmall.into());
+    }
+    let remaining_give_b = give_b - paid_by_b;
+    let remaining_receive_b = receive_b - fill_x;
+
+    let binding_a = [bump_a.to_le()];
+    let seed_a = escrow_signer_seeds(maker_a.address(), &binding_a);
+    let seeds_a = Signer::from(&seed_a);
+
+    let binding_b = [bump_b.to_le()];
+    let seed_b = escrow_signer_seeds(maker_b.address(), &binding_b);
+    let seeds_b = Signer::from(&seed_b);
+
+    pinocchio_token::instructions::Transfer {
+        from: vault_a,
+        to: maker_b_ata_b,
+        authority: escrow_a,
+        amount: fill_x,
+    }
+    .invoke_signed(&[seeds_a.clone()])?;
+
+    // Y that reaches A, the surplus only stays with A when no keeper takes it
+    let received_by_a = match keeper_ata {
+        Some(keeper_ata) if surplus > 0 => {
+            pinocchio_token::instructions::Transfer {
+                from: vault_b,
+                to: maker_a_ata_b,
+                authority: escrow_b,
+                amount: owed_to_a,
+            }
+            .invoke_signed(&[seeds_b.clone()])?;
+
+            pinocchio_token::instructions::Transfer {
+                from: vault_b,
+                to: keeper_ata,
+                authority: escrow_b,
+                amount: surplus,
+            }
+            .invoke_signed(&[seeds_b.clone()])?;
+
+            owed_to_a
+        }
+        _ => {
+            pinocchio_token::instructions::Transfer {
+                from: vault_b,
+                to: maker_a_ata_b,
+                authority: escrow_b,
+                amount: paid_by_b,
+            }
+            .invoke_signed(&[seeds_b.clone()])?;
+
+            paid_by_b
+        }
+    };
+
+    {
+        let mut state_a = Escrow::load_mut(escrow_a)?;
+        state_a.set_amount_to_give(remaining_give_a);
+        state_a.set_amount_to_receive(remaining_receive_a);
+    }
+    {
+        let mut state_b = Escrow::load_mut(escrow_b)?;
+        state_b.set_amount_to_give(remaining_give_b);
+        state_b.set_amount_to_receive(remaining_receive_b);
+    }
+
+    // market entries carry the amounts, a partially filled escrow is listed again at what
+    // it still offers
+    for (escrow, market_acc, remaining_give) in [
+        (escrow_a, market_a, remaining_give_a),
+        (escrow_b, market_b, remaining_give_b),
+    ] {
+        if let Some(market_acc) = market_acc {
+            delist_escrow(market_acc, escrow)?;
+            if remaining_give > 0 {
+                list_escrow(market_acc, escrow)?;
+            }
+        }
+    }
+
+    // Close filled vaults, lamports go back to their makers
+    if remaining_give_a == 0 {
+        pinocchio_token::instructions::CloseAccount {
+            account: vault_a,
+            destination: maker_a,
+            authority: escrow_a,
+        }
+        .invoke_signed(&[seeds_a.clone()])?;
+    }
+    if remaining_give_b == 0 {
+        pinocchio_token::instructions::CloseAccount {
+            account: vault_b,
+            destination: maker_b,
+            authority: escrow_b,
+        }
+        .invoke_signed(&[seeds_b.clone()])?;
+    }
+
+    // each side is logged as taken by the other maker for the filled amounts
+    EscrowEvent::new(
+        EscrowEventKind::Match,
+        escrow_a.address(),
+        maker_a.address(),
+        maker_b.address(),
+        &mint_x,
+        &mint_y,
+        fill_x,
+        received_by_a,
+    )?
+    .emit();
+
+    EscrowEvent::new(
+        EscrowEventKind::Match,
+        escrow_b.address(),
+        maker_b.address(),
+        maker_a.address(),
+        &mint_y,
+        &mint_x,
+        paid_by_b,
+        fill_x,
+    )?
+    .emit();
+
+    Ok(())
+}
+
+/// Splits the next trailing account off `remaining` when the escrow needs it.
+fn split_if<'a>(
+    remaining: &mut &'a [AccountView],
+    needed: bool,
+) -> Result<Option<&'a AccountView>, ProgramError> {
+    if !needed {
+        return Ok(None);
+    }
+
+    let (account, rest) = remaining
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    *remaining = rest;
+    Ok(Some(account))
+}
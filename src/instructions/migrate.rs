@@ -0,0 +1,78 @@
+use pinocchio::{
+    error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    AccountView, ProgramResult,
+};
+use pinocchio_system::instructions::Transfer;
+
+use crate::{
+    pda::create_escrow_address_with_bump,
+    state::{account_type, AccountType, Escrow, DISCRIMINATOR_LEN},
+};
+
+/// Converts an escrow written before account discriminators into the current layout.
+/// The maker signs and pays for the extra rent, the escrow's contents are carried over
+/// unchanged.
+pub fn process_migrate_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [maker, escrow_acc, _system_program @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !maker.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !escrow_acc.owned_by(&crate::ID) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // current accounts are left alone
+    if account_type(escrow_acc).is_ok() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let legacy_len = escrow_acc.data_len();
+    if !(Escrow::LEGACY_MIN_LEN..=Escrow::LEN - DISCRIMINATOR_LEN).contains(&legacy_len) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // -- legacy layout --//
+    // 0-31 maker
+    // 32-111 mints and amounts
+    // 112 bump
+    // MakeV2 accounts were stored without a discriminator too and can have the same length,
+    // their byte 112 is part of an amount, so only a bump that derives this escrow from its
+    // maker proves a v1 escrow
+    {
+        let data = escrow_acc.try_borrow()?;
+        if data[..32] != *maker.address().as_array() {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if create_escrow_address_with_bump(maker.address(), data[Escrow::LEGACY_MIN_LEN - 1])
+            != *escrow_acc.address()
+        {
+            return Err(ProgramError::InvalidSeeds);
+        }
+    }
+
+    // fields added after the legacy layout are zeroed, which is their unset value
+    let mut migrated = [0u8; Escrow::LEN];
+    migrated[..DISCRIMINATOR_LEN].copy_from_slice(&AccountType::Escrow.discriminator());
+    migrated[DISCRIMINATOR_LEN..DISCRIMINATOR_LEN + legacy_len]
+        .copy_from_slice(&escrow_acc.try_borrow()?);
+
+    let rent = Rent::get()?.try_minimum_balance(Escrow::LEN)?;
+    if escrow_acc.lamports() < rent {
+        Transfer {
+            from: maker,
+            to: escrow_acc,
+            lamports: rent - escrow_acc.lamports(),
+        }
+        .invoke()?;
+    }
+
+    escrow_acc.resize(Escrow::LEN)?;
+    escrow_acc.try_borrow_mut()?.copy_from_slice(&migrated);
+
+    Ok(())
+}
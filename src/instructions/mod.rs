@@ -1,11 +1,23 @@
+pub mod approve;
+pub mod arbitration;
+pub mod claim;
+pub mod codec;
+pub mod htlc;
 pub mod make;
+pub mod market;
+pub mod match_escrows;
+pub mod migrate;
 pub mod refund;
+pub mod set_delegate;
 pub mod take;
+pub mod take_many;
 pub mod wincode_v2;
 
+pub use codec::*;
 pub use make::*;
 use pinocchio::error::ProgramError;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EscrowInstrctions {
     Make = 0,
     Take = 1,
@@ -13,6 +25,21 @@ pub enum EscrowInstrctions {
     MakeV2 = 3,
     TakeV2 = 4,
     RefundV2 = 5,
+    Claim = 6,
+    Release = 7,
+    Reverse = 8,
+    Approve = 9,
+    MakeHtlc = 10,
+    Redeem = 11,
+    TakeMany = 12,
+    Match = 13,
+    InitMarket = 14,
+    BestOffers = 15,
+    SetDelegate = 16,
+    TakeFor = 17,
+    Migrate = 18,
+    MakeWithExtensions = 19,
+    MakeWithPayer = 20,
 }
 
 impl TryFrom<&u8> for EscrowInstrctions {
@@ -26,7 +53,88 @@ impl TryFrom<&u8> for EscrowInstrctions {
             3 => Ok(EscrowInstrctions::MakeV2),
             4 => Ok(EscrowInstrctions::TakeV2),
             5 => Ok(EscrowInstrctions::RefundV2),
+            6 => Ok(EscrowInstrctions::Claim),
+            7 => Ok(EscrowInstrctions::Release),
+            8 => Ok(EscrowInstrctions::Reverse),
+            9 => Ok(EscrowInstrctions::Approve),
+            10 => Ok(EscrowInstrctions::MakeHtlc),
+            11 => Ok(EscrowInstrctions::Redeem),
+            12 => Ok(EscrowInstrctions::TakeMany),
+            13 => Ok(EscrowInstrctions::Match),
+            14 => Ok(EscrowInstrctions::InitMarket),
+            15 => Ok(EscrowInstrctions::BestOffers),
+            16 => Ok(EscrowInstrctions::SetDelegate),
+            17 => Ok(EscrowInstrctions::TakeFor),
+            18 => Ok(EscrowInstrctions::Migrate),
+            19 => Ok(EscrowInstrctions::MakeWithExtensions),
+            20 => Ok(EscrowInstrctions::MakeWithPayer),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
 }
+
+/// Length of an Anchor instruction discriminator, see [`EscrowInstrctions::sighash`].
+pub const SIGHASH_LEN: usize = 8;
+
+impl EscrowInstrctions {
+    pub const ALL: [EscrowInstrctions; 21] = [
+        EscrowInstrctions::Make,
+        EscrowInstrctions::Take,
+        EscrowInstrctions::Refund,
+        EscrowInstrctions::MakeV2,
+        EscrowInstrctions::TakeV2,
+        EscrowInstrctions::RefundV2,
+        EscrowInstrctions::Claim,
+        EscrowInstrctions::Release,
+        EscrowInstrctions::Reverse,
+        EscrowInstrctions::Approve,
+        EscrowInstrctions::MakeHtlc,
+        EscrowInstrctions::Redeem,
+        EscrowInstrctions::TakeMany,
+        EscrowInstrctions::Match,
+        EscrowInstrctions::InitMarket,
+        EscrowInstrctions::BestOffers,
+        EscrowInstrctions::SetDelegate,
+        EscrowInstrctions::TakeFor,
+        EscrowInstrctions::Migrate,
+        EscrowInstrctions::MakeWithExtensions,
+        EscrowInstrctions::MakeWithPayer,
+    ];
+
+    /// Anchor's `sha256("global:<name>")[..8]` of the instruction's IDL name. With the
+    /// `anchor` feature the program accepts it in place of the tag byte.
+    pub const fn sighash(self) -> [u8; SIGHASH_LEN] {
+        match self {
+            EscrowInstrctions::Make => [138, 227, 232, 77, 223, 166, 96, 197],
+            EscrowInstrctions::Take => [149, 226, 52, 104, 6, 142, 230, 39],
+            EscrowInstrctions::Refund => [2, 96, 183, 251, 63, 208, 46, 46],
+            EscrowInstrctions::MakeV2 => [111, 58, 254, 79, 2, 222, 228, 109],
+            EscrowInstrctions::TakeV2 => [56, 25, 107, 135, 190, 118, 171, 120],
+            EscrowInstrctions::RefundV2 => [31, 65, 178, 189, 90, 76, 189, 145],
+            EscrowInstrctions::Claim => [62, 198, 214, 193, 213, 159, 108, 210],
+            EscrowInstrctions::Release => [253, 249, 15, 206, 28, 127, 193, 241],
+            EscrowInstrctions::Reverse => [137, 149, 242, 82, 88, 95, 221, 145],
+            EscrowInstrctions::Approve => [69, 74, 217, 36, 115, 117, 97, 76],
+            EscrowInstrctions::MakeHtlc => [115, 222, 249, 37, 160, 125, 231, 166],
+            EscrowInstrctions::Redeem => [184, 12, 86, 149, 70, 196, 97, 225],
+            EscrowInstrctions::TakeMany => [16, 165, 89, 124, 152, 139, 216, 151],
+            EscrowInstrctions::Match => [211, 200, 178, 49, 142, 145, 48, 254],
+            EscrowInstrctions::InitMarket => [33, 253, 15, 116, 89, 25, 127, 236],
+            EscrowInstrctions::BestOffers => [238, 250, 160, 136, 44, 11, 21, 253],
+            EscrowInstrctions::SetDelegate => [242, 30, 46, 76, 108, 235, 128, 181],
+            EscrowInstrctions::TakeFor => [80, 195, 105, 220, 110, 125, 12, 251],
+            EscrowInstrctions::Migrate => [155, 234, 231, 146, 236, 158, 162, 30],
+            EscrowInstrctions::MakeWithExtensions => [252, 103, 208, 251, 92, 116, 202, 103],
+            EscrowInstrctions::MakeWithPayer => [176, 75, 104, 205, 14, 253, 148, 250],
+        }
+    }
+
+    /// Splits off a leading Anchor sighash, `None` when `data` doesn't start with one.
+    pub fn from_sighash(data: &[u8]) -> Option<(Self, &[u8])> {
+        let (sighash, rest) = data.split_at_checked(SIGHASH_LEN)?;
+        Self::ALL
+            .into_iter()
+            .find(|ix| ix.sighash() == sighash)
+            .map(|ix| (ix, rest))
+    }
+}
@@ -1,47 +1,153 @@
 use pinocchio::{
-    cpi::{Seed, Signer},
+    cpi::Signer,
     error::ProgramError,
-    AccountView, ProgramResult,
+    sysvars::{clock::Clock, Sysvar},
+    AccountView, Address, ProgramResult,
 };
-use pinocchio_log::log;
 
-use crate::state::Escrow;
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    instructions::{market::delist_escrow, wincode_v2::process_refund_v2_instruction},
+    pda::escrow_signer_seeds,
+    state::{account_type, close_account, AccountState, AccountType, Approvals, Escrow},
+};
 
-pub fn process_refund_instruction(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
-    log!("enterd refund");
-    let [maker, escrow_acc, maker_ata, escrow_ata, _token_program, _system_program, _associated_token_program @ ..] =
+pub fn process_refund_instruction(accounts: &[AccountView]) -> ProgramResult {
+    if let Some(escrow_acc) = accounts.get(1) {
+        if account_type(escrow_acc)? == AccountType::WincodeEscrow {
+            return process_refund_v2_instruction(accounts);
+        }
+    }
+
+    let [maker, escrow_acc, maker_ata, escrow_ata, token_program, system_program, _associated_token_program @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    if !maker.is_signer() {
-        return Err(ProgramError::IncorrectAuthority);
-    }
+    let (
+        amount_to_refund,
+        amount_to_receive,
+        bump,
+        is_listed,
+        approval_threshold,
+        delegate,
+        mint_a,
+        mint_b,
+    ) = {
+        let escrow_state = Escrow::load(escrow_acc)?;
 
-    let (amount_to_refund, bump) = {
-        let escrow_state = Escrow::from_account_info(escrow_acc)?;
-        let maker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(maker_ata)?;
-
-        if *maker_ata_state.owner() != escrow_state.maker()
-            || *maker.address() != escrow_state.maker()
-        {
+        if *maker.address() != escrow_state.maker() {
             return Err(ProgramError::IllegalOwner);
         }
 
+        // once taken the vault is owed to the taker (vesting or held by an arbiter)
+        if escrow_state.has_taker() {
+            return Err(EscrowError::AlreadyTaken.into());
+        }
+
+        // the recipient keeps the right to redeem until the timeout
+        if escrow_state.is_htlc() && Clock::get()?.unix_timestamp < escrow_state.htlc_timeout() {
+            return Err(EscrowError::HtlcNotExpired.into());
+        }
+
         let amount_to_refund = escrow_state.amount_to_give();
         let bump = escrow_state.bump;
 
-        (amount_to_refund, bump)
+        let delegate = escrow_state.has_delegate().then(|| escrow_state.delegate());
+
+        (
+            amount_to_refund,
+            escrow_state.amount_to_receive(),
+            bump,
+            escrow_state.is_listed(),
+            escrow_state.approval_threshold,
+            delegate,
+            escrow_state.mint_a(),
+            escrow_state.mint_b(),
+        )
+    };
+
+    // Optional trailing accounts, in order: the market when the escrow is listed,
+    // the approvals PDA when it has an approval threshold, the delegate when it signs
+    // instead of the maker, then mint_a when maker_ata doesn't exist yet
+    let mut remaining = accounts.get(7..).unwrap_or(&[]);
+
+    let market_acc = if is_listed {
+        let (market_acc, rest) = remaining
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        remaining = rest;
+        Some(market_acc)
+    } else {
+        None
+    };
+
+    let approvals_acc = if approval_threshold > 0 {
+        let (approvals_acc, rest) = remaining
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if Approvals::load(approvals_acc)?.escrow() != *escrow_acc.address() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        remaining = rest;
+        Some(approvals_acc)
+    } else {
+        None
     };
 
+    // funds still go back to the maker when the delegate signs
+    let signer = if maker.is_signer() {
+        maker
+    } else {
+        let (delegate_acc, rest) = remaining
+            .split_first()
+            .ok_or(ProgramError::IncorrectAuthority)?;
+        if !delegate_acc.is_signer() || Some(*delegate_acc.address()) != delegate {
+            return Err(ProgramError::IncorrectAuthority);
+        }
+        remaining = rest;
+        delegate_acc
+    };
+
+    // Create the maker's mint_a account on the fly, rent paid by the signer
+    if maker_ata.data_len() == 0 {
+        let mint_a_acc = remaining
+            .first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if *mint_a_acc.address() != mint_a {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        pinocchio_associated_token_account::instructions::CreateIdempotent {
+            funding_account: signer,
+            account: maker_ata,
+            wallet: maker,
+            mint: mint_a_acc,
+            system_program,
+            token_program,
+        }
+        .invoke()?;
+    }
+
+    {
+        let maker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(maker_ata)?;
+        if *maker_ata_state.owner() != *maker.address() {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    if let Some(market_acc) = market_acc {
+        delist_escrow(market_acc, escrow_acc)?;
+    }
+    if let Some(approvals_acc) = approvals_acc {
+        close_account(approvals_acc, maker)?;
+    }
+
     // Build seeds for PDA signing
     let binding = [bump.to_le()];
-    let seed = [
-        Seed::from(b"escrow"),
-        Seed::from(maker.address().as_array()),
-        Seed::from(&binding),
-    ];
+    let seed = escrow_signer_seeds(maker.address(), &binding);
     let seeds = Signer::from(&seed);
 
     pinocchio_token::instructions::Transfer {
@@ -60,5 +166,17 @@ pub fn process_refund_instruction(accounts: &[AccountView], _data: &[u8]) -> Pro
     }
     .invoke_signed(&[seeds.clone()])?;
 
+    EscrowEvent::new(
+        EscrowEventKind::Refund,
+        escrow_acc.address(),
+        maker.address(),
+        &Address::default(),
+        &mint_a,
+        &mint_b,
+        amount_to_refund,
+        amount_to_receive,
+    )?
+    .emit();
+
     Ok(())
 }
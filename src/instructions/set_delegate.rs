@@ -0,0 +1,26 @@
+use pinocchio::{error::ProgramError, AccountView, ProgramResult};
+
+use crate::state::{AccountState, Escrow};
+
+/// `delegate` may refund in place of the maker, all zeroes removes it.
+pub fn process_set_delegate_instruction(
+    accounts: &[AccountView],
+    delegate: [u8; 32],
+) -> ProgramResult {
+    let [maker, escrow_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !maker.is_signer() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+    if *maker.address() != escrow_state.maker() {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    escrow_state.set_delegate(&delegate.into());
+
+    Ok(())
+}
@@ -1,24 +1,113 @@
 use pinocchio::{
-    cpi::{Seed, Signer},
+    cpi::Signer,
     error::ProgramError,
-    AccountView, ProgramResult,
+    sysvars::{clock::Clock, Sysvar},
+    AccountView, Address, ProgramResult,
 };
 
-use crate::state::Escrow;
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    instructions::{market::delist_escrow, wincode_v2::process_take_v2_instruction},
+    pda::escrow_signer_seeds,
+    state::{
+        account_type, close_account, designated_taker, expiry, get_extension, AccountState,
+        AccountType, Approvals, Escrow, ExtensionType,
+    },
+};
+
+pub fn process_take_instruction(accounts: &[AccountView]) -> ProgramResult {
+    // escrows written by MakeV2 share the PDA, their leading byte routes them to TakeV2
+    if let Some(escrow_acc) = accounts.get(2) {
+        if account_type(escrow_acc)? == AccountType::WincodeEscrow {
+            return process_take_v2_instruction(accounts);
+        }
+    }
+    take(accounts, None)
+}
+
+/// Take paid and signed by `taker`, with mint_a going to the leading `recipient` account.
+/// `taker_ata_a` must then belong to the recipient, who is also the taker of record
+/// for vesting claims and arbitrated escrows.
+pub fn process_take_for_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [recipient, accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    take(accounts, Some(recipient))
+}
 
-pub fn process_take_instruction(accounts: &[AccountView], _data: &[u8]) -> ProgramResult {
+/// Rejects a take of an expired escrow, or by anyone but its designated taker.
+pub(crate) fn check_take_extensions(escrow_acc: &AccountView, taker: &Address) -> ProgramResult {
+    let escrow_state = Escrow::load(escrow_acc)?;
+    let extensions = escrow_state.extensions();
+
+    if let Some(value) = get_extension(extensions, ExtensionType::Expiry)? {
+        if Clock::get()?.unix_timestamp >= expiry(value) {
+            return Err(EscrowError::Expired.into());
+        }
+    }
+    if let Some(value) = get_extension(extensions, ExtensionType::DesignatedTaker)? {
+        if designated_taker(value) != *taker {
+            return Err(EscrowError::NotDesignatedTaker.into());
+        }
+    }
+
+    Ok(())
+}
+
+fn take(accounts: &[AccountView], recipient: Option<&AccountView>) -> ProgramResult {
     // Account destructuring
-    let [taker, maker, escrow_acc, mint_a, mint_b, taker_ata_a, taker_ata_b, escrow_ata_a, maker_ata_b, _token_program, _system_program, _associated_token_program @ ..] =
+    let [taker, maker, escrow_acc, mint_a, mint_b, taker_ata_a, taker_ata_b, escrow_ata_a, maker_ata_b, token_program, system_program, _associated_token_program @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
+    let recipient = recipient.unwrap_or(taker);
+
+    // Create missing mint_a / mint_b accounts on the fly, rent paid by the taker
+    if taker_ata_a.data_len() == 0 {
+        pinocchio_associated_token_account::instructions::CreateIdempotent {
+            funding_account: taker,
+            account: taker_ata_a,
+            wallet: recipient,
+            mint: mint_a,
+            system_program,
+            token_program,
+        }
+        .invoke()?;
+    }
+    if maker_ata_b.data_len() == 0 {
+        pinocchio_associated_token_account::instructions::CreateIdempotent {
+            funding_account: taker,
+            account: maker_ata_b,
+            wallet: maker,
+            mint: mint_b,
+            system_program,
+            token_program,
+        }
+        .invoke()?;
+    }
 
     // Account validation
     // We are not validating the pda because we are checking the maker against the maker ata
     // so it will be fine if the maker in the escrow gets his stated tokens to him
-    let (amount_to_receive, amount_to_give, bump) = {
-        let escrow_state = Escrow::from_account_info(escrow_acc)?;
+    let (
+        amount_to_receive,
+        amount_to_give,
+        bump,
+        is_vesting,
+        has_arbiter,
+        approval_threshold,
+        is_listed,
+    ) = {
+        let escrow_state = Escrow::load(escrow_acc)?;
+        if escrow_state.has_taker() {
+            return Err(EscrowError::AlreadyTaken.into());
+        }
+        if escrow_state.is_htlc() {
+            return Err(EscrowError::HashLocked.into());
+        }
+
         let taker_ata_a_state =
             pinocchio_token::state::TokenAccount::from_account_view(taker_ata_a)?;
         let taker_ata_b_state =
@@ -29,12 +118,13 @@ pub fn process_take_instruction(accounts: &[AccountView], _data: &[u8]) -> Progr
         if *maker_ata_b_state.owner() != escrow_state.maker()
             || taker_ata_a_state.mint() != mint_a.address()
             || taker_ata_b_state.mint() != mint_b.address()
+            || *mint_b.address() != escrow_state.mint_b()
             || *maker.address() != escrow_state.maker()
         {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        if taker_ata_a_state.owner() != taker.address() {
+        if taker_ata_a_state.owner() != recipient.address() {
             return Err(ProgramError::IllegalOwner);
         }
 
@@ -42,18 +132,107 @@ pub fn process_take_instruction(accounts: &[AccountView], _data: &[u8]) -> Progr
         let amount_to_give = escrow_state.amount_to_give();
         let bump = escrow_state.bump;
 
-        (amount_to_receive, amount_to_give, bump)
+        (
+            amount_to_receive,
+            amount_to_give,
+            bump,
+            escrow_state.is_vesting(),
+            escrow_state.has_arbiter(),
+            escrow_state.approval_threshold,
+            escrow_state.is_listed(),
+        )
+    };
+    check_take_extensions(escrow_acc, recipient.address())?;
+
+    // Optional trailing accounts, in order: escrow_ata_b when the escrow has an arbiter,
+    // the market when the escrow is listed, then the approvals PDA (closed once the
+    // threshold is met) followed by any approvers signing this transaction
+    let mut remaining = accounts.get(12..).unwrap_or(&[]);
+
+    let escrow_ata_b = if has_arbiter {
+        let (escrow_ata_b, rest) = remaining
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        remaining = rest;
+        Some(escrow_ata_b)
+    } else {
+        None
     };
 
+    let market_acc = if is_listed {
+        let (market_acc, rest) = remaining
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        remaining = rest;
+        Some(market_acc)
+    } else {
+        None
+    };
+
+    if approval_threshold > 0 {
+        let (approvals_acc, approvers) = remaining
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        {
+            let approvals_state = Approvals::load(approvals_acc)?;
+            if approvals_state.escrow() != *escrow_acc.address() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if approvals_state.count_approvals(approvers) < approval_threshold {
+                return Err(EscrowError::NotEnoughApprovals.into());
+            }
+        }
+        // approvals only gate the take, the maker gets the rent back
+        close_account(approvals_acc, maker)?;
+    }
+
+    // the escrow stops being an open offer as soon as it is taken
+    if let Some(market_acc) = market_acc {
+        delist_escrow(market_acc, escrow_acc)?;
+    }
+
+    EscrowEvent::new(
+        EscrowEventKind::Take,
+        escrow_acc.address(),
+        maker.address(),
+        recipient.address(),
+        mint_a.address(),
+        mint_b.address(),
+        amount_to_give,
+        amount_to_receive,
+    )?
+    .emit();
+
     // Build seeds for PDA signing
     let binding = [bump.to_le()];
-    let seed = [
-        Seed::from(b"escrow"),
-        Seed::from(maker.address().as_array()),
-        Seed::from(&binding),
-    ];
+    let seed = escrow_signer_seeds(maker.address(), &binding);
     let seeds = Signer::from(&seed);
 
+    // Arbitrated escrows hold the payment in a second vault until Release or Reverse
+    if let Some(escrow_ata_b) = escrow_ata_b {
+        pinocchio_associated_token_account::instructions::CreateIdempotent {
+            funding_account: taker,
+            account: escrow_ata_b,
+            wallet: escrow_acc,
+            mint: mint_b,
+            system_program,
+            token_program,
+        }
+        .invoke()?;
+
+        pinocchio_token::instructions::Transfer {
+            from: taker_ata_b,
+            to: escrow_ata_b,
+            authority: taker,
+            amount: amount_to_receive,
+        }
+        .invoke()?;
+
+        let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+        escrow_state.set_taker(recipient.address());
+        return Ok(());
+    }
+
     // Taker sends amount_to_receive to maker
     pinocchio_token::instructions::Transfer {
         from: taker_ata_b,
@@ -63,6 +242,14 @@ pub fn process_take_instruction(accounts: &[AccountView], _data: &[u8]) -> Progr
     }
     .invoke()?;
 
+    // Vesting escrows keep mint_a in the vault, the taker withdraws it through Claim
+    if is_vesting {
+        let mut escrow_state = Escrow::load_mut(escrow_acc)?;
+        escrow_state.set_taker(recipient.address());
+        escrow_state.set_vesting_start(Clock::get()?.unix_timestamp);
+        return Ok(());
+    }
+
     // Escrow sends amount_to_give to taker
     pinocchio_token::instructions::Transfer {
         from: escrow_ata_a,
@@ -0,0 +1,129 @@
+use pinocchio::{cpi::Signer, error::ProgramError, AccountView, ProgramResult};
+
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, EscrowEventKind},
+    instructions::{market::delist_escrow, take::check_take_extensions},
+    pda::escrow_signer_seeds,
+    state::{AccountState, Escrow},
+};
+
+/// Accounts per escrow after the shared taker prefix: escrow, vault, maker, maker_ata_b
+const GROUP_LEN: usize = 4;
+
+/// The groups can be followed by the market of the pair, needed once any escrow is listed.
+pub fn process_take_many_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [taker, mint_a, mint_b, taker_ata_a, taker_ata_b, _token_program, groups @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (groups, market_acc) = match groups.split_last() {
+        Some((market_acc, rest)) if groups.len() % GROUP_LEN == 1 => (rest, Some(market_acc)),
+        _ => (groups, None),
+    };
+
+    if groups.is_empty() || groups.len() % GROUP_LEN != 0 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    {
+        let taker_ata_a_state =
+            pinocchio_token::state::TokenAccount::from_account_view(taker_ata_a)?;
+        let taker_ata_b_state =
+            pinocchio_token::state::TokenAccount::from_account_view(taker_ata_b)?;
+
+        if taker_ata_a_state.mint() != mint_a.address()
+            || taker_ata_b_state.mint() != mint_b.address()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if taker_ata_a_state.owner() != taker.address() {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Any failing escrow aborts the whole instruction, so the batch settles atomically
+    for group in groups.chunks_exact(GROUP_LEN) {
+        let [escrow_acc, escrow_ata_a, maker, maker_ata_b] = group else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        let (amount_to_receive, amount_to_give, bump, is_listed) = {
+            let escrow_state = Escrow::load(escrow_acc)?;
+            if !escrow_state.is_plain() {
+                return Err(EscrowError::UnsupportedEscrow.into());
+            }
+
+            let maker_ata_b_state =
+                pinocchio_token::state::TokenAccount::from_account_view(maker_ata_b)?;
+
+            if *maker_ata_b_state.owner() != escrow_state.maker()
+                || *maker.address() != escrow_state.maker()
+                || *mint_a.address() != escrow_state.mint_a()
+                || *mint_b.address() != escrow_state.mint_b()
+            {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            (
+                escrow_state.amount_to_receive(),
+                escrow_state.amount_to_give(),
+                escrow_state.bump,
+                escrow_state.is_listed(),
+            )
+        };
+        check_take_extensions(escrow_acc, taker.address())?;
+
+        // Build seeds for PDA signing
+        let binding = [bump.to_le()];
+        let seed = escrow_signer_seeds(maker.address(), &binding);
+        let seeds = Signer::from(&seed);
+
+        // Taker sends amount_to_receive to maker
+        pinocchio_token::instructions::Transfer {
+            from: taker_ata_b,
+            to: maker_ata_b,
+            authority: taker,
+            amount: amount_to_receive,
+        }
+        .invoke()?;
+
+        // Escrow sends amount_to_give to taker
+        pinocchio_token::instructions::Transfer {
+            from: escrow_ata_a,
+            to: taker_ata_a,
+            authority: escrow_acc,
+            amount: amount_to_give,
+        }
+        .invoke_signed(&[seeds.clone()])?;
+
+        // Close the vault token account, lamports go back to maker
+        pinocchio_token::instructions::CloseAccount {
+            account: escrow_ata_a,
+            destination: maker,
+            authority: escrow_acc,
+        }
+        .invoke_signed(&[seeds.clone()])?;
+
+        if is_listed {
+            let market_acc = market_acc.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            delist_escrow(market_acc, escrow_acc)?;
+        }
+
+        EscrowEvent::new(
+            EscrowEventKind::Take,
+            escrow_acc.address(),
+            maker.address(),
+            taker.address(),
+            mint_a.address(),
+            mint_b.address(),
+            amount_to_give,
+            amount_to_receive,
+        )?
+        .emit();
+    }
+
+    Ok(())
+}
@@ -1,26 +1,36 @@
-use ::wincode::SchemaRead;
+use ::wincode::{SchemaRead, SchemaWrite};
 use pinocchio::{
-    cpi::{Seed, Signer},
+    cpi::Signer,
     error::ProgramError,
     sysvars::{rent::Rent, Sysvar},
-    AccountView, ProgramResult,
+    AccountView, Address, ProgramResult,
 };
-use pinocchio_associated_token_account::instructions::Create;
-use pinocchio_log::log;
-use pinocchio_pubkey::derive_address;
+use pinocchio_associated_token_account::instructions::CreateIdempotent;
 use pinocchio_system::instructions::CreateAccount;
 use pinocchio_token::instructions::Transfer;
 
-use crate::{state::WincodeEscrow, ID};
+use crate::{
+    events::{EscrowEvent, EscrowEventKind},
+    pda::{create_escrow_address_with_bump, escrow_signer_seeds},
+    state::{AccountState, WincodeEscrow},
+    ID,
+};
 
-#[derive(SchemaRead)]
+#[derive(Clone, Copy, Debug, PartialEq, SchemaRead, SchemaWrite)]
 pub struct MakeInstructionData {
     pub bump: u8,
     pub amount_to_receive: [u8; 64],
     pub amount_to_give: [u8; 64],
 }
 
-pub fn process_make_v2_instruction(accounts: &[AccountView], data: &[u8]) -> ProgramResult {
+impl MakeInstructionData {
+    pub const LEN: usize = 1 + 64 + 64;
+}
+
+pub fn process_make_v2_instruction(
+    accounts: &[AccountView],
+    ix_data: MakeInstructionData,
+) -> ProgramResult {
     let [maker, escrow_acc, mint_a, mint_b, maker_ata, escrow_ata, system_program, token_program, _assoociated_token_program @ ..] =
         accounts
     else {
@@ -31,11 +41,6 @@ pub fn process_make_v2_instruction(accounts: &[AccountView], data: &[u8]) -> Pro
         return Err(ProgramError::IncorrectAuthority);
     }
 
-    log!("entered v2");
-
-    let ix_data = ::wincode::deserialize::<MakeInstructionData>(data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-
     let bump = ix_data.bump;
     let amount_to_receive = ix_data.amount_to_receive;
     let amount_to_give = ix_data.amount_to_give;
@@ -50,19 +55,12 @@ pub fn process_make_v2_instruction(accounts: &[AccountView], data: &[u8]) -> Pro
         }
     }
 
-    let seeds: [&[u8]; 3] = [b"escrow", maker.address().as_array(), &[bump]];
-    let expected_escrow = derive_address(&seeds, None, ID.as_array());
-
-    if escrow_acc.address().as_array() != &expected_escrow {
+    if *escrow_acc.address() != create_escrow_address_with_bump(maker.address(), bump) {
         return Err(ProgramError::InvalidAccountData);
     }
 
     let bump_seed = [bump];
-    let signer_seeds = [
-        Seed::from(b"escrow"),
-        Seed::from(maker.address().as_array()),
-        Seed::from(&bump_seed),
-    ];
+    let signer_seeds = escrow_signer_seeds(maker.address(), &bump_seed);
     let signer = Signer::from(&signer_seeds[..]);
 
     CreateAccount {
@@ -74,16 +72,18 @@ pub fn process_make_v2_instruction(accounts: &[AccountView], data: &[u8]) -> Pro
     }
     .invoke_signed(&[signer])?;
 
-    let escrow_state = WincodeEscrow::from_account_info(escrow_acc)?;
-
-    escrow_state.maker = *maker.address().as_array();
-    escrow_state.mint_a = *mint_a.address().as_array();
-    escrow_state.mint_b = *mint_b.address().as_array();
-    escrow_state.amount_to_receive = amount_to_receive;
-    escrow_state.amount_to_give = amount_to_give;
-    escrow_state.bump = bump;
+    {
+        let mut escrow_state = WincodeEscrow::init(escrow_acc)?;
+
+        escrow_state.maker = *maker.address().as_array();
+        escrow_state.mint_a = *mint_a.address().as_array();
+        escrow_state.mint_b = *mint_b.address().as_array();
+        escrow_state.amount_to_receive = amount_to_receive;
+        escrow_state.amount_to_give = amount_to_give;
+        escrow_state.bump = bump;
+    }
 
-    Create {
+    CreateIdempotent {
         funding_account: maker,
         account: escrow_ata,
         wallet: escrow_acc,
@@ -101,5 +101,17 @@ pub fn process_make_v2_instruction(accounts: &[AccountView], data: &[u8]) -> Pro
     }
     .invoke()?;
 
+    EscrowEvent::new(
+        EscrowEventKind::Make,
+        escrow_acc.address(),
+        maker.address(),
+        &Address::default(),
+        mint_a.address(),
+        mint_b.address(),
+        amount,
+        u64::from_le_bytes(amount_to_receive[..8].try_into().unwrap()),
+    )?
+    .emit();
+
     Ok(())
 }
@@ -1,3 +1,7 @@
 pub mod make;
+pub mod refund;
+pub mod take;
 
-pub use make::*;
\ No newline at end of file
+pub use make::*;
+pub use refund::*;
+pub use take::*;
\ No newline at end of file
@@ -0,0 +1,77 @@
+use pinocchio::{cpi::Signer, error::ProgramError, AccountView, Address, ProgramResult};
+use pinocchio_token::instructions::{CloseAccount, Transfer};
+
+use crate::{
+    events::{EscrowEvent, EscrowEventKind},
+    pda::escrow_signer_seeds,
+    state::{AccountState, WincodeEscrow},
+};
+
+/// Refund of an escrow written by MakeV2, signed by the maker.
+pub fn process_refund_v2_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [maker, escrow_acc, maker_ata, escrow_ata, _token_program @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !maker.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (amount_to_refund, amount_to_receive, bump, mint_a, mint_b) = {
+        let escrow_state = WincodeEscrow::load(escrow_acc)?;
+        if maker.address().as_array() != &escrow_state.maker {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        (
+            escrow_state.amount_to_give(),
+            escrow_state.amount_to_receive(),
+            escrow_state.bump,
+            Address::from(escrow_state.mint_a),
+            Address::from(escrow_state.mint_b),
+        )
+    };
+
+    {
+        let maker_ata_state = pinocchio_token::state::TokenAccount::from_account_view(maker_ata)?;
+        if maker_ata_state.owner() != maker.address() {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if *maker_ata_state.mint() != mint_a {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let bump_seed = [bump];
+    let signer_seeds = escrow_signer_seeds(maker.address(), &bump_seed);
+    let signer = Signer::from(&signer_seeds[..]);
+
+    Transfer {
+        from: escrow_ata,
+        to: maker_ata,
+        authority: escrow_acc,
+        amount: amount_to_refund,
+    }
+    .invoke_signed(&[signer.clone()])?;
+
+    CloseAccount {
+        account: escrow_ata,
+        destination: maker,
+        authority: escrow_acc,
+    }
+    .invoke_signed(&[signer])?;
+
+    EscrowEvent::new(
+        EscrowEventKind::Refund,
+        escrow_acc.address(),
+        maker.address(),
+        &Address::default(),
+        &mint_a,
+        &mint_b,
+        amount_to_refund,
+        amount_to_receive,
+    )?
+    .emit();
+
+    Ok(())
+}
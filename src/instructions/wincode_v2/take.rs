@@ -0,0 +1,108 @@
+use pinocchio::{cpi::Signer, error::ProgramError, AccountView, Address, ProgramResult};
+use pinocchio_associated_token_account::instructions::CreateIdempotent;
+use pinocchio_token::instructions::{CloseAccount, Transfer};
+
+use crate::{
+    events::{EscrowEvent, EscrowEventKind},
+    pda::escrow_signer_seeds,
+    state::{AccountState, WincodeEscrow},
+};
+
+/// Take of an escrow written by MakeV2, same accounts as the v1 Take.
+pub fn process_take_v2_instruction(accounts: &[AccountView]) -> ProgramResult {
+    let [taker, maker, escrow_acc, mint_a, mint_b, taker_ata_a, taker_ata_b, escrow_ata_a, maker_ata_b, token_program, system_program, _associated_token_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !taker.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    for (account, wallet, mint) in [(taker_ata_a, taker, mint_a), (maker_ata_b, maker, mint_b)] {
+        if account.data_len() == 0 {
+            CreateIdempotent {
+                funding_account: taker,
+                account,
+                wallet,
+                mint,
+                system_program,
+                token_program,
+            }
+            .invoke()?;
+        }
+    }
+
+    let (amount_to_receive, amount_to_give, bump) = {
+        let escrow_state = WincodeEscrow::load(escrow_acc)?;
+
+        let taker_ata_a_state =
+            pinocchio_token::state::TokenAccount::from_account_view(taker_ata_a)?;
+        let taker_ata_b_state =
+            pinocchio_token::state::TokenAccount::from_account_view(taker_ata_b)?;
+        let maker_ata_b_state =
+            pinocchio_token::state::TokenAccount::from_account_view(maker_ata_b)?;
+
+        if maker.address().as_array() != &escrow_state.maker
+            || mint_a.address().as_array() != &escrow_state.mint_a
+            || mint_b.address().as_array() != &escrow_state.mint_b
+            || maker_ata_b_state.owner() != maker.address()
+            || maker_ata_b_state.mint() != mint_b.address()
+            || taker_ata_a_state.mint() != mint_a.address()
+            || taker_ata_b_state.mint() != mint_b.address()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if taker_ata_a_state.owner() != taker.address() {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        (
+            escrow_state.amount_to_receive(),
+            escrow_state.amount_to_give(),
+            escrow_state.bump,
+        )
+    };
+
+    let bump_seed = [bump];
+    let signer_seeds = escrow_signer_seeds(maker.address(), &bump_seed);
+    let signer = Signer::from(&signer_seeds[..]);
+
+    Transfer {
+        from: taker_ata_b,
+        to: maker_ata_b,
+        authority: taker,
+        amount: amount_to_receive,
+    }
+    .invoke()?;
+
+    Transfer {
+        from: escrow_ata_a,
+        to: taker_ata_a,
+        authority: escrow_acc,
+        amount: amount_to_give,
+    }
+    .invoke_signed(&[signer.clone()])?;
+
+    CloseAccount {
+        account: escrow_ata_a,
+        destination: maker,
+        authority: escrow_acc,
+    }
+    .invoke_signed(&[signer])?;
+
+    EscrowEvent::new(
+        EscrowEventKind::Take,
+        escrow_acc.address(),
+        maker.address(),
+        taker.address(),
+        mint_a.address(),
+        mint_b.address(),
+        amount_to_give,
+        amount_to_receive,
+    )?
+    .emit();
+
+    Ok(())
+}
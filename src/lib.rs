@@ -1,13 +1,21 @@
 #![allow(unexpected_cfgs)]
-use pinocchio::{
-    address::declare_id, entrypoint, error::ProgramError, AccountView, Address, ProgramResult,
-};
+use pinocchio::{address::declare_id, entrypoint, AccountView, Address, ProgramResult};
 
-use crate::instructions::EscrowInstrctions;
+use crate::instructions::EscrowInstruction;
 
+#[cfg(feature = "client")]
+pub mod client;
+pub mod cpi;
+pub mod error;
+pub mod events;
+#[cfg(feature = "idl")]
+pub mod idl;
 pub mod instructions;
+pub mod pda;
 pub mod state;
 
+// programs calling the escrow through `cpi` bring their own entrypoint
+#[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 
 declare_id!("1cxuHMSWBe1gkX3pC19zFcahwPGBWMA9x4SvxhBiCn3");
@@ -19,20 +27,60 @@ pub fn process_instruction(
 ) -> ProgramResult {
     assert_eq!(program_id, &ID);
 
-    //get the DESCRIMINATOR from the Instruction Data
-    let (descriminator, data) = instruction_data
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
-    match EscrowInstrctions::try_from(descriminator)? {
-        EscrowInstrctions::Make => instructions::make::process_make_instruction(accounts, data)?,
-        EscrowInstrctions::Take => instructions::take::process_take_instruction(accounts, data)?,
-        EscrowInstrctions::Refund => {
-            instructions::refund::process_refund_instruction(accounts, data)?
-        }
-        EscrowInstrctions::MakeV2 => {
-            instructions::wincode_v2::make::process_make_v2_instruction(accounts, data)?
-        }
-        _ => Err(ProgramError::InvalidInstructionData)?,
+    match EscrowInstruction::unpack(instruction_data)? {
+        EscrowInstruction::Make(args) => {
+            instructions::make::process_make_instruction(accounts, args)?
+        }
+        EscrowInstruction::Take => instructions::take::process_take_instruction(accounts)?,
+        EscrowInstruction::Refund => instructions::refund::process_refund_instruction(accounts)?,
+        EscrowInstruction::MakeV2(ix_data) => {
+            instructions::wincode_v2::make::process_make_v2_instruction(accounts, ix_data)?
+        }
+        EscrowInstruction::Claim => instructions::claim::process_claim_instruction(accounts)?,
+        EscrowInstruction::Release => {
+            instructions::arbitration::process_release_instruction(accounts)?
+        }
+        EscrowInstruction::Reverse => {
+            instructions::arbitration::process_reverse_instruction(accounts)?
+        }
+        EscrowInstruction::Approve => instructions::approve::process_approve_instruction(accounts)?,
+        EscrowInstruction::MakeHtlc(ix_data) => {
+            instructions::htlc::process_make_htlc_instruction(accounts, ix_data)?
+        }
+        EscrowInstruction::Redeem { preimage } => {
+            instructions::htlc::process_redeem_instruction(accounts, preimage)?
+        }
+        EscrowInstruction::TakeMany => {
+            instructions::take_many::process_take_many_instruction(accounts)?
+        }
+        EscrowInstruction::Match => {
+            instructions::match_escrows::process_match_instruction(accounts)?
+        }
+        EscrowInstruction::InitMarket { bump } => {
+            instructions::market::process_init_market_instruction(accounts, bump)?
+        }
+        EscrowInstruction::BestOffers { n } => {
+            instructions::market::process_best_offers_instruction(accounts, n)?
+        }
+        EscrowInstruction::SetDelegate { delegate } => {
+            instructions::set_delegate::process_set_delegate_instruction(accounts, delegate)?
+        }
+        EscrowInstruction::TakeFor => instructions::take::process_take_for_instruction(accounts)?,
+        EscrowInstruction::TakeV2 => {
+            instructions::wincode_v2::take::process_take_v2_instruction(accounts)?
+        }
+        EscrowInstruction::RefundV2 => {
+            instructions::wincode_v2::refund::process_refund_v2_instruction(accounts)?
+        }
+        EscrowInstruction::Migrate => instructions::migrate::process_migrate_instruction(accounts)?,
+        EscrowInstruction::MakeWithExtensions { extensions, make } => {
+            instructions::make::process_make_with_extensions_instruction(
+                accounts, extensions, make,
+            )?
+        }
+        EscrowInstruction::MakeWithPayer(args) => {
+            instructions::make::process_make_with_payer_instruction(accounts, args)?
+        }
     };
     Ok(())
 }
@@ -0,0 +1,61 @@
+//! Escrow PDA and vault derivation shared by the program and off-chain clients.
+
+use pinocchio::{cpi::Seed, Address};
+use pinocchio_pubkey::derive_address;
+
+pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const MARKET_SEED: &[u8] = b"market";
+
+/// Escrow address of `maker` for a known bump, cheap enough to check on-chain.
+pub fn create_escrow_address_with_bump(maker: &Address, bump: u8) -> Address {
+    Address::from(derive_address(
+        &[ESCROW_SEED, maker.as_ref(), &[bump]],
+        None,
+        &crate::ID.to_bytes(),
+    ))
+}
+
+/// Canonical escrow address of `maker` and its bump.
+#[cfg(any(target_os = "solana", feature = "client"))]
+pub fn find_escrow_address(maker: &Address) -> (Address, u8) {
+    find_program_address(&[ESCROW_SEED, maker.as_ref()], &crate::ID)
+}
+
+/// Canonical market address of the (mint_a, mint_b) pair and its bump.
+#[cfg(any(target_os = "solana", feature = "client"))]
+pub fn find_market_address(mint_a: &Address, mint_b: &Address) -> (Address, u8) {
+    find_program_address(&[MARKET_SEED, mint_a.as_ref(), mint_b.as_ref()], &crate::ID)
+}
+
+/// The escrow's associated token account for `mint`, which holds that side of the trade.
+#[cfg(any(target_os = "solana", feature = "client"))]
+pub fn escrow_vault_address(escrow: &Address, mint: &Address) -> Address {
+    find_program_address(
+        &[escrow.as_ref(), pinocchio_token::ID.as_ref(), mint.as_ref()],
+        &pinocchio_associated_token_account::ID,
+    )
+    .0
+}
+
+/// Seeds the escrow signs its vault CPIs with, `bump` has to outlive the returned seeds.
+pub fn escrow_signer_seeds<'a>(maker: &'a Address, bump: &'a [u8; 1]) -> [Seed<'a>; 3] {
+    [
+        Seed::from(ESCROW_SEED),
+        Seed::from(maker.as_ref()),
+        Seed::from(bump),
+    ]
+}
+
+#[cfg(target_os = "solana")]
+fn find_program_address(seeds: &[&[u8]], program_id: &Address) -> (Address, u8) {
+    Address::find_program_address(seeds, program_id)
+}
+
+#[cfg(all(not(target_os = "solana"), feature = "client"))]
+fn find_program_address(seeds: &[&[u8]], program_id: &Address) -> (Address, u8) {
+    let (address, bump) = solana_pubkey::Pubkey::find_program_address(
+        seeds,
+        &solana_pubkey::Pubkey::new_from_array(program_id.to_bytes()),
+    );
+    (Address::from(address.to_bytes()), bump)
+}
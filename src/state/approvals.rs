@@ -0,0 +1,58 @@
+use pinocchio::{AccountView, Address};
+
+use crate::state::DISCRIMINATOR_LEN;
+
+pub const MAX_APPROVERS: usize = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Approvals {
+    discriminator: [u8; DISCRIMINATOR_LEN],
+    escrow: [u8; 32],
+    approvers: [[u8; 32]; MAX_APPROVERS],
+    pub approver_count: u8,
+    approved: u8, // bitmap over approvers
+    pub bump: u8,
+}
+
+impl Approvals {
+    pub const LEN: usize = DISCRIMINATOR_LEN + 32 + 32 * MAX_APPROVERS + 1 + 1 + 1;
+
+    pub fn escrow(&self) -> Address {
+        Address::from(self.escrow)
+    }
+
+    pub fn set_escrow(&mut self, escrow: &Address) {
+        self.escrow.copy_from_slice(escrow.as_ref());
+    }
+
+    pub fn set_approver(&mut self, index: usize, approver: &Address) {
+        self.approvers[index].copy_from_slice(approver.as_ref());
+    }
+
+    pub fn position(&self, approver: &Address) -> Option<usize> {
+        self.approvers[..self.approver_count as usize]
+            .iter()
+            .position(|a| a == approver.as_array())
+    }
+
+    pub fn is_approved(&self, index: usize) -> bool {
+        self.approved & (1 << index) != 0
+    }
+
+    pub fn approve(&mut self, index: usize) {
+        self.approved |= 1 << index;
+    }
+
+    /// Approvers that either recorded `Approve` earlier or signed the current transaction.
+    pub fn count_approvals(&self, signers: &[AccountView]) -> u8 {
+        (0..self.approver_count as usize)
+            .filter(|&i| {
+                self.is_approved(i)
+                    || signers
+                        .iter()
+                        .any(|s| s.is_signer() && s.address().as_array() == &self.approvers[i])
+            })
+            .count() as u8
+    }
+}
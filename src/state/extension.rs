@@ -0,0 +1,137 @@
+use pinocchio::{error::ProgramError, Address};
+
+use crate::state::Escrow;
+
+/// Size of the type and length prefix of every extension entry.
+pub const EXTENSION_HEADER_LEN: usize = 2 + 2;
+
+pub const MAX_MEMO_LEN: usize = 256;
+
+/// Optional escrow features stored after the fixed [`Escrow`] layout, each as a
+/// u16 type, a u16 length and the value, all little endian. Make allocates only the
+/// entries the maker asked for, so adding a type here never changes existing accounts.
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionType {
+    /// i64 unix timestamp from which the escrow can no longer be taken
+    Expiry = 1,
+    /// The only address allowed to take the escrow
+    DesignatedTaker = 2,
+    /// Free form bytes for the maker, at most [`MAX_MEMO_LEN`]
+    Memo = 3,
+}
+
+impl ExtensionType {
+    fn is_valid_len(self, len: usize) -> bool {
+        match self {
+            ExtensionType::Expiry => len == 8,
+            ExtensionType::DesignatedTaker => len == 32,
+            ExtensionType::Memo => len <= MAX_MEMO_LEN,
+        }
+    }
+}
+
+impl TryFrom<u16> for ExtensionType {
+    type Error = ProgramError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ExtensionType::Expiry),
+            2 => Ok(ExtensionType::DesignatedTaker),
+            3 => Ok(ExtensionType::Memo),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Walks the entries of an extension region, stops at the first malformed one.
+pub struct Extensions<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Extensions<'a> {
+    type Item = Result<(ExtensionType, &'a [u8]), ProgramError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = self.data;
+        let entry = (|| {
+            let header = data
+                .get(..EXTENSION_HEADER_LEN)
+                .ok_or(ProgramError::InvalidAccountData)?;
+            let extension_type =
+                ExtensionType::try_from(u16::from_le_bytes([header[0], header[1]]))?;
+            let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+            let value = data
+                .get(EXTENSION_HEADER_LEN..EXTENSION_HEADER_LEN + len)
+                .ok_or(ProgramError::InvalidAccountData)?;
+            if !extension_type.is_valid_len(len) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            Ok((extension_type, value, EXTENSION_HEADER_LEN + len))
+        })();
+
+        match entry {
+            Ok((extension_type, value, entry_len)) => {
+                self.data = &data[entry_len..];
+                Some(Ok((extension_type, value)))
+            }
+            Err(e) => {
+                self.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Entries of an extension region, see [`escrow_extensions`] for an escrow account's.
+pub fn extensions(data: &[u8]) -> Extensions<'_> {
+    Extensions { data }
+}
+
+/// Extension region of escrow account data, empty for an escrow without extensions.
+pub fn escrow_extensions(data: &[u8]) -> &[u8] {
+    data.get(Escrow::LEN..).unwrap_or(&[])
+}
+
+/// Value of the `extension_type` entry in `data`, if the maker requested it.
+pub fn get_extension(
+    data: &[u8],
+    extension_type: ExtensionType,
+) -> Result<Option<&[u8]>, ProgramError> {
+    for entry in extensions(data) {
+        let (entry_type, value) = entry?;
+        if entry_type == extension_type {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks a requested extension region: known types, correct sizes and no duplicates.
+pub fn validate_extensions(data: &[u8]) -> Result<(), ProgramError> {
+    let mut seen = 0u16;
+    for entry in extensions(data) {
+        let (extension_type, _) = entry.map_err(|_| ProgramError::InvalidInstructionData)?;
+        let bit = 1 << extension_type as u16;
+        if seen & bit != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        seen |= bit;
+    }
+
+    Ok(())
+}
+
+pub fn expiry(value: &[u8]) -> i64 {
+    i64::from_le_bytes(value.try_into().unwrap())
+}
+
+pub fn designated_taker(value: &[u8]) -> Address {
+    Address::from(<[u8; 32]>::try_from(value).unwrap())
+}
@@ -0,0 +1,170 @@
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use pinocchio::{
+    account::{Ref, RefMut},
+    error::ProgramError,
+    AccountView, ProgramResult,
+};
+
+use crate::state::{
+    escrow_extensions, AccountType, Approvals, Escrow, Market, WincodeEscrow, DISCRIMINATOR_LEN,
+};
+
+/// Program account layouts, identified by their leading [`AccountType`] discriminator.
+pub trait AccountState: Sized {
+    const ACCOUNT_TYPE: AccountType;
+
+    /// Read access to an initialized account owned by this program.
+    fn load(account_info: &AccountView) -> Result<AccountRef<'_, Self>, ProgramError> {
+        check_owner(account_info)?;
+        let data = account_info.try_borrow()?;
+        check_data::<Self>(&data, Self::ACCOUNT_TYPE)?;
+
+        Ok(AccountRef {
+            data,
+            state: PhantomData,
+        })
+    }
+
+    /// Write access to an initialized account owned by this program.
+    fn load_mut(account_info: &AccountView) -> Result<AccountRefMut<'_, Self>, ProgramError> {
+        check_owner(account_info)?;
+        let data = account_info.try_borrow_mut()?;
+        check_data::<Self>(&data, Self::ACCOUNT_TYPE)?;
+
+        Ok(AccountRefMut {
+            data,
+            state: PhantomData,
+        })
+    }
+
+    /// Stamps the discriminator on an account freshly allocated for `Self`.
+    fn init(account_info: &AccountView) -> Result<AccountRefMut<'_, Self>, ProgramError> {
+        check_owner(account_info)?;
+        let mut data = account_info.try_borrow_mut()?;
+        check_data::<Self>(&data, AccountType::Uninitialized)
+            .map_err(|_| ProgramError::AccountAlreadyInitialized)?;
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&Self::ACCOUNT_TYPE.discriminator());
+
+        Ok(AccountRefMut {
+            data,
+            state: PhantomData,
+        })
+    }
+}
+
+impl AccountState for Escrow {
+    const ACCOUNT_TYPE: AccountType = AccountType::Escrow;
+}
+
+impl AccountState for WincodeEscrow {
+    const ACCOUNT_TYPE: AccountType = AccountType::WincodeEscrow;
+}
+
+impl AccountState for Approvals {
+    const ACCOUNT_TYPE: AccountType = AccountType::Approvals;
+}
+
+impl AccountState for Market {
+    const ACCOUNT_TYPE: AccountType = AccountType::Market;
+}
+
+// the guards cast the account data in place, which needs byte alignment and the
+// whole struct inside the checked length
+const _: () = {
+    assert!(core::mem::align_of::<Escrow>() == 1);
+    assert!(core::mem::align_of::<WincodeEscrow>() == 1);
+    assert!(core::mem::align_of::<Approvals>() == 1);
+    assert!(core::mem::align_of::<Market>() == 1);
+    assert!(core::mem::size_of::<Escrow>() == Escrow::LEN);
+    assert!(core::mem::size_of::<Approvals>() == Approvals::LEN);
+    assert!(core::mem::size_of::<Market>() == Market::LEN);
+};
+
+fn check_owner(account_info: &AccountView) -> Result<(), ProgramError> {
+    if !account_info.owned_by(&crate::ID) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    Ok(())
+}
+
+/// Closes an account owned by this program, its lamports go to `destination`.
+/// Nothing may still borrow the account data.
+pub fn close_account(account_info: &AccountView, destination: &AccountView) -> ProgramResult {
+    check_owner(account_info)?;
+    let lamports = destination
+        .lamports()
+        .checked_add(account_info.lamports())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    destination.set_lamports(lamports);
+    account_info.close()
+}
+
+fn check_data<T: AccountState>(data: &[u8], expected: AccountType) -> Result<(), ProgramError> {
+    if !T::ACCOUNT_TYPE.is_valid_len(data.len())
+        || data[..DISCRIMINATOR_LEN] != expected.discriminator()
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+/// Shared borrow of a program account, released when the guard is dropped.
+pub struct AccountRef<'a, T> {
+    data: Ref<'a, [u8]>,
+    state: PhantomData<T>,
+}
+
+/// Exclusive borrow of a program account, released when the guard is dropped.
+/// Drop it before a CPI that passes the account along.
+pub struct AccountRefMut<'a, T> {
+    data: RefMut<'a, [u8]>,
+    state: PhantomData<T>,
+}
+
+pub type EscrowRef<'a> = AccountRef<'a, Escrow>;
+pub type EscrowRefMut<'a> = AccountRefMut<'a, Escrow>;
+
+impl<T: AccountState> Deref for AccountRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the length and alignment were checked when the guard was created
+        unsafe { &*(self.data.as_ptr() as *const T) }
+    }
+}
+
+impl<T: AccountState> Deref for AccountRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the length and alignment were checked when the guard was created
+        unsafe { &*(self.data.as_ptr() as *const T) }
+    }
+}
+
+impl<T: AccountState> DerefMut for AccountRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the length and alignment were checked when the guard was created
+        unsafe { &mut *(self.data.as_mut_ptr() as *mut T) }
+    }
+}
+
+impl EscrowRef<'_> {
+    /// Extension region after the fixed layout.
+    pub fn extensions(&self) -> &[u8] {
+        escrow_extensions(&self.data)
+    }
+}
+
+impl EscrowRefMut<'_> {
+    /// Extension region after the fixed layout, Make fills it in right after `init`.
+    pub fn extensions_mut(&mut self) -> &mut [u8] {
+        &mut self.data[Escrow::LEN..]
+    }
+}
@@ -0,0 +1,52 @@
+//! JSON friendly `Serialize` impls, enabled with the `serde` feature.
+//! Addresses are base58, the hashlock is hex and amounts are plain numbers.
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::state::{Escrow, WincodeEscrow};
+
+fn base58(bytes: impl AsRef<[u8]>) -> String {
+    bs58::encode(bytes).into_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+impl Serialize for Escrow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Escrow", 18)?;
+        state.serialize_field("maker", &base58(self.maker()))?;
+        state.serialize_field("mint_a", &base58(self.mint_a()))?;
+        state.serialize_field("mint_b", &base58(self.mint_b()))?;
+        state.serialize_field("amount_to_receive", &self.amount_to_receive())?;
+        state.serialize_field("amount_to_give", &self.amount_to_give())?;
+        state.serialize_field("bump", &self.bump)?;
+        state.serialize_field("taker", &base58(self.taker()))?;
+        state.serialize_field("vesting_cliff", &self.vesting_cliff())?;
+        state.serialize_field("vesting_duration", &self.vesting_duration())?;
+        state.serialize_field("vesting_start", &self.vesting_start())?;
+        state.serialize_field("claimed", &self.claimed())?;
+        state.serialize_field("arbiter", &base58(self.arbiter()))?;
+        state.serialize_field("approval_threshold", &self.approval_threshold)?;
+        state.serialize_field("hashlock", &hex(&self.hashlock()))?;
+        state.serialize_field("htlc_timeout", &self.htlc_timeout())?;
+        state.serialize_field("htlc_recipient", &base58(self.htlc_recipient()))?;
+        state.serialize_field("listed", &self.is_listed())?;
+        state.serialize_field("delegate", &base58(self.delegate()))?;
+        state.end()
+    }
+}
+
+impl Serialize for WincodeEscrow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("WincodeEscrow", 6)?;
+        state.serialize_field("maker", &base58(self.maker))?;
+        state.serialize_field("mint_a", &base58(self.mint_a))?;
+        state.serialize_field("mint_b", &base58(self.mint_b))?;
+        state.serialize_field("amount_to_receive", &self.amount_to_receive())?;
+        state.serialize_field("amount_to_give", &self.amount_to_give())?;
+        state.serialize_field("bump", &self.bump)?;
+        state.end()
+    }
+}
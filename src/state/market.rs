@@ -0,0 +1,127 @@
+use pinocchio::{error::ProgramError, Address};
+
+use crate::{error::EscrowError, state::DISCRIMINATOR_LEN};
+
+pub const MARKET_CAPACITY: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MarketEntry {
+    escrow: [u8; 32],
+    amount_to_give: [u8; 8],
+    amount_to_receive: [u8; 8],
+}
+
+impl MarketEntry {
+    pub const LEN: usize = 32 + 8 + 8;
+
+    pub fn new(escrow: &Address, amount_to_give: u64, amount_to_receive: u64) -> Self {
+        Self {
+            escrow: *escrow.as_array(),
+            amount_to_give: amount_to_give.to_le_bytes(),
+            amount_to_receive: amount_to_receive.to_le_bytes(),
+        }
+    }
+
+    pub fn escrow(&self) -> Address {
+        Address::from(self.escrow)
+    }
+
+    pub fn amount_to_give(&self) -> u64 {
+        u64::from_le_bytes(self.amount_to_give)
+    }
+
+    pub fn amount_to_receive(&self) -> u64 {
+        u64::from_le_bytes(self.amount_to_receive)
+    }
+
+    /// True when `self` asks less mint_b per mint_a than `other`.
+    pub fn is_cheaper_than(&self, other: &MarketEntry) -> bool {
+        (self.amount_to_receive() as u128 * other.amount_to_give() as u128)
+            < (other.amount_to_receive() as u128 * self.amount_to_give() as u128)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, Self::LEN) }
+    }
+}
+
+/// Open escrows of one (mint_a, mint_b) pair, cheapest offer first.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Market {
+    discriminator: [u8; DISCRIMINATOR_LEN],
+    mint_a: [u8; 32],
+    mint_b: [u8; 32],
+    pub bump: u8,
+    len: u8,
+    entries: [MarketEntry; MARKET_CAPACITY],
+}
+
+impl Market {
+    pub const LEN: usize = DISCRIMINATOR_LEN + 32 + 32 + 1 + 1 + MarketEntry::LEN * MARKET_CAPACITY;
+
+    pub fn mint_a(&self) -> Address {
+        Address::from(self.mint_a)
+    }
+
+    pub fn set_mint_a(&mut self, mint_a: &Address) {
+        self.mint_a.copy_from_slice(mint_a.as_ref());
+    }
+
+    pub fn mint_b(&self) -> Address {
+        Address::from(self.mint_b)
+    }
+
+    pub fn set_mint_b(&mut self, mint_b: &Address) {
+        self.mint_b.copy_from_slice(mint_b.as_ref());
+    }
+
+    pub fn entries(&self) -> &[MarketEntry] {
+        &self.entries[..self.len as usize]
+    }
+
+    pub fn contains(&self, escrow: &Address) -> bool {
+        self.entries()
+            .iter()
+            .any(|e| &e.escrow == escrow.as_array())
+    }
+
+    /// Inserts behind every entry at the same or a better price, so equal prices stay FIFO.
+    /// A full market evicts its worst entry for a cheaper one and returns it, so filling
+    /// the slots only blocks listings the spammer is willing to undercut.
+    pub fn insert(&mut self, entry: MarketEntry) -> Result<Option<MarketEntry>, ProgramError> {
+        let mut len = self.len as usize;
+        let mut evicted = None;
+        if len == MARKET_CAPACITY {
+            if !entry.is_cheaper_than(&self.entries[len - 1]) {
+                return Err(EscrowError::MarketFull.into());
+            }
+            len -= 1;
+            evicted = Some(self.entries[len]);
+            self.len -= 1;
+        }
+
+        let index = self.entries[..len]
+            .iter()
+            .position(|e| entry.is_cheaper_than(e))
+            .unwrap_or(len);
+        self.entries.copy_within(index..len, index + 1);
+        self.entries[index] = entry;
+        self.len += 1;
+        Ok(evicted)
+    }
+
+    pub fn remove(&mut self, escrow: &Address) -> Result<(), ProgramError> {
+        let len = self.len as usize;
+        let index = self.entries[..len]
+            .iter()
+            .position(|e| &e.escrow == escrow.as_array())
+            .ok_or(EscrowError::NotListed)?;
+
+        self.entries.copy_within(index + 1..len, index);
+        self.entries[len - 1] = MarketEntry::default();
+        self.len -= 1;
+        Ok(())
+    }
+}
@@ -1,35 +1,179 @@
 use pinocchio::{error::ProgramError, AccountView};
 
+pub mod approvals;
+pub mod extension;
+pub mod guard;
+#[cfg(feature = "serde")]
+mod json;
+pub mod market;
 pub mod wincode_escrow;
 
+pub use approvals::*;
+pub use extension::*;
+pub use guard::*;
+pub use market::*;
 use wincode::SchemaRead;
 pub use wincode_escrow::*;
 
+/// Size of the leading discriminator of every account the program owns: the
+/// [`AccountType`] byte, or its Anchor discriminator with the `anchor` feature.
+#[cfg(not(feature = "anchor"))]
+pub const DISCRIMINATOR_LEN: usize = 1;
+#[cfg(feature = "anchor")]
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Leading byte of every account the program owns. A new layout of an existing
+/// type gets a new value, so the byte is also the account's version.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountType {
+    Uninitialized = 0,
+    Escrow = 1,
+    WincodeEscrow = 2,
+    Approvals = 3,
+    Market = 4,
+}
+
+impl AccountType {
+    pub const ALL: [AccountType; 5] = [
+        AccountType::Uninitialized,
+        AccountType::Escrow,
+        AccountType::WincodeEscrow,
+        AccountType::Approvals,
+        AccountType::Market,
+    ];
+
+    /// What the account data starts with, see [`DISCRIMINATOR_LEN`].
+    #[cfg(not(feature = "anchor"))]
+    pub const fn discriminator(self) -> [u8; DISCRIMINATOR_LEN] {
+        [self as u8]
+    }
+
+    /// What the account data starts with, see [`DISCRIMINATOR_LEN`].
+    #[cfg(feature = "anchor")]
+    pub const fn discriminator(self) -> [u8; DISCRIMINATOR_LEN] {
+        self.anchor_discriminator()
+    }
+
+    /// Anchor's `sha256("account:<Name>")[..8]`, all zeroes for a fresh account.
+    pub const fn anchor_discriminator(self) -> [u8; 8] {
+        match self {
+            AccountType::Uninitialized => [0; 8],
+            AccountType::Escrow => [31, 213, 123, 187, 186, 22, 218, 155],
+            AccountType::WincodeEscrow => [170, 44, 160, 18, 13, 212, 92, 22],
+            AccountType::Approvals => [197, 100, 140, 225, 34, 243, 199, 110],
+            AccountType::Market => [219, 190, 213, 55, 0, 227, 198, 154],
+        }
+    }
+
+    /// Type of account data from its leading discriminator, the length is not checked.
+    pub fn from_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let discriminator = data
+            .get(..DISCRIMINATOR_LEN)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        AccountType::ALL
+            .into_iter()
+            .find(|account_type| account_type.discriminator() == discriminator)
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+
+    pub const fn account_len(self) -> usize {
+        match self {
+            AccountType::Uninitialized => 0,
+            AccountType::Escrow => Escrow::LEN,
+            AccountType::WincodeEscrow => WincodeEscrow::LEN,
+            AccountType::Approvals => Approvals::LEN,
+            AccountType::Market => Market::LEN,
+        }
+    }
+
+    /// Escrows may be followed by their extension region, other accounts have a fixed size.
+    pub const fn is_valid_len(self, len: usize) -> bool {
+        match self {
+            AccountType::Uninitialized => false,
+            AccountType::Escrow => len >= Escrow::LEN,
+            _ => len == self.account_len(),
+        }
+    }
+}
+
+impl TryFrom<&u8> for AccountType {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AccountType::Uninitialized),
+            1 => Ok(AccountType::Escrow),
+            2 => Ok(AccountType::WincodeEscrow),
+            3 => Ok(AccountType::Approvals),
+            4 => Ok(AccountType::Market),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// Type of an initialized program account, from its discriminator and size.
+pub fn account_type(account_info: &AccountView) -> Result<AccountType, ProgramError> {
+    let data = account_info.try_borrow()?;
+    let account_type = AccountType::from_data(&data)?;
+    if !account_type.is_valid_len(data.len()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(account_type)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq,SchemaRead)]
 pub struct Escrow {
+    discriminator: [u8; DISCRIMINATOR_LEN],
     maker: [u8; 32],
     mint_a: [u8; 32],
     mint_b: [u8; 32],
     amount_to_receive: [u8; 8],
     amount_to_give: [u8; 8],
     pub bump: u8,
+    taker: [u8; 32],
+    vesting_cliff: [u8; 8],
+    vesting_duration: [u8; 8],
+    vesting_start: [u8; 8],
+    claimed: [u8; 8],
+    arbiter: [u8; 32],
+    pub approval_threshold: u8,
+    hashlock: [u8; 32],
+    htlc_timeout: [u8; 8],
+    htlc_recipient: [u8; 32],
+    pub listed: u8,
+    delegate: [u8; 32],
 }
 
 impl Escrow {
-    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 1;
+    pub const LEN: usize = DISCRIMINATOR_LEN
+        + (32 + 32 + 32 + 8 + 8 + 1 + 32 + 8 + 8 + 8 + 8 + 32 + 1 + 32 + 8 + 32 + 1 + 32);
+
+    /// Size of the original layout. Every later field was appended, so an escrow written
+    /// before the discriminator existed is a prefix of the current layout after the
+    /// discriminator.
+    pub const LEGACY_MIN_LEN: usize = 32 + 32 + 32 + 8 + 8 + 1;
 
-    pub fn from_account_info(account_info: &AccountView) -> Result<&mut Self, ProgramError> {
-        let mut data = account_info.try_borrow_mut()?;
-        if data.len() != Escrow::LEN {
+    /// Owned copy of the escrow in `data`, for clients reading fetched accounts.
+    /// Extensions are read separately with [`escrow_extensions`].
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < Escrow::LEN || AccountType::from_data(data)? != AccountType::Escrow {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        if (data.as_ptr() as usize) % core::mem::align_of::<Self>() != 0 {
-            return Err(ProgramError::InvalidAccountData);
+        wincode::deserialize::<Escrow>(&data[..Escrow::LEN])
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// [`Escrow::try_from_bytes`] for an account that has to be owned by this program.
+    pub fn try_from_account(owner: &pinocchio::Address, data: &[u8]) -> Result<Self, ProgramError> {
+        if *owner != crate::ID {
+            return Err(ProgramError::IllegalOwner);
         }
 
-        Ok(unsafe { &mut *(data.as_mut_ptr() as *mut Self) })
+        Self::try_from_bytes(data)
     }
 
     pub fn maker(&self) -> pinocchio::Address {
@@ -71,4 +215,143 @@ impl Escrow {
     pub fn set_amount_to_give(&mut self, amount: u64) {
         self.amount_to_give = amount.to_le_bytes();
     }
+
+    pub fn taker(&self) -> pinocchio::Address {
+        pinocchio::Address::from(self.taker)
+    }
+
+    pub fn set_taker(&mut self, taker: &pinocchio::Address) {
+        self.taker.copy_from_slice(taker.as_ref());
+    }
+
+    // the taker is only recorded for escrows that stay open after Take
+    pub fn has_taker(&self) -> bool {
+        self.taker != [0u8; 32]
+    }
+
+    pub fn vesting_cliff(&self) -> u64 {
+        u64::from_le_bytes(self.vesting_cliff)
+    }
+
+    pub fn set_vesting_cliff(&mut self, seconds: u64) {
+        self.vesting_cliff = seconds.to_le_bytes();
+    }
+
+    pub fn vesting_duration(&self) -> u64 {
+        u64::from_le_bytes(self.vesting_duration)
+    }
+
+    pub fn set_vesting_duration(&mut self, seconds: u64) {
+        self.vesting_duration = seconds.to_le_bytes();
+    }
+
+    pub fn vesting_start(&self) -> i64 {
+        i64::from_le_bytes(self.vesting_start)
+    }
+
+    pub fn set_vesting_start(&mut self, timestamp: i64) {
+        self.vesting_start = timestamp.to_le_bytes();
+    }
+
+    pub fn claimed(&self) -> u64 {
+        u64::from_le_bytes(self.claimed)
+    }
+
+    pub fn set_claimed(&mut self, amount: u64) {
+        self.claimed = amount.to_le_bytes();
+    }
+
+    pub fn arbiter(&self) -> pinocchio::Address {
+        pinocchio::Address::from(self.arbiter)
+    }
+
+    pub fn set_arbiter(&mut self, arbiter: &pinocchio::Address) {
+        self.arbiter.copy_from_slice(arbiter.as_ref());
+    }
+
+    pub fn has_arbiter(&self) -> bool {
+        self.arbiter != [0u8; 32]
+    }
+
+    pub fn delegate(&self) -> pinocchio::Address {
+        pinocchio::Address::from(self.delegate)
+    }
+
+    pub fn set_delegate(&mut self, delegate: &pinocchio::Address) {
+        self.delegate.copy_from_slice(delegate.as_ref());
+    }
+
+    // a delegate can refund on the maker's behalf
+    pub fn has_delegate(&self) -> bool {
+        self.delegate != [0u8; 32]
+    }
+
+    pub fn hashlock(&self) -> [u8; 32] {
+        self.hashlock
+    }
+
+    pub fn set_hashlock(&mut self, hashlock: &[u8; 32]) {
+        self.hashlock = *hashlock;
+    }
+
+    pub fn htlc_timeout(&self) -> i64 {
+        i64::from_le_bytes(self.htlc_timeout)
+    }
+
+    pub fn set_htlc_timeout(&mut self, timestamp: i64) {
+        self.htlc_timeout = timestamp.to_le_bytes();
+    }
+
+    pub fn htlc_recipient(&self) -> pinocchio::Address {
+        pinocchio::Address::from(self.htlc_recipient)
+    }
+
+    pub fn set_htlc_recipient(&mut self, recipient: &pinocchio::Address) {
+        self.htlc_recipient.copy_from_slice(recipient.as_ref());
+    }
+
+    // hash time-locked escrows are redeemed with a preimage instead of being taken
+    pub fn is_htlc(&self) -> bool {
+        self.hashlock != [0u8; 32]
+    }
+
+    /// True when the escrow settles in full on Take with no conditions, the only extra
+    /// account it can need is its market.
+    pub fn is_plain(&self) -> bool {
+        !self.has_taker()
+            && !self.is_vesting()
+            && !self.has_arbiter()
+            && self.approval_threshold == 0
+            && !self.is_htlc()
+    }
+
+    // listed escrows have to be removed from their market when they close
+    pub fn is_listed(&self) -> bool {
+        self.listed != 0
+    }
+
+    pub fn is_vesting(&self) -> bool {
+        self.vesting_duration() > 0
+    }
+
+    /// Amount of mint_a unlocked for the taker at `now`.
+    /// Nothing is unlocked before the cliff, after that it grows linearly
+    /// from the Take timestamp until the full duration has passed.
+    pub fn vested_amount(&self, now: i64) -> u64 {
+        let total = self.amount_to_give();
+        let duration = self.vesting_duration();
+        if duration == 0 {
+            return total;
+        }
+
+        let elapsed = now.saturating_sub(self.vesting_start()).max(0) as u64;
+        if elapsed < self.vesting_cliff() {
+            return 0;
+        }
+        if elapsed >= duration {
+            return total;
+        }
+
+        (total as u128 * elapsed as u128 / duration as u128) as u64
+    }
 }
@@ -1,9 +1,14 @@
-use pinocchio::{error::ProgramError, AccountView};
-use wincode::SchemaRead;
+use pinocchio::{error::ProgramError, Address};
+use wincode::{SchemaRead, SchemaWrite};
 
+use crate::state::{AccountType, DISCRIMINATOR_LEN};
+
+/// State written by MakeV2. The wincode encoding is the raw field bytes in order,
+/// so the account holds exactly `LEN` bytes and the state guards can cast it in place.
 #[repr(C)]
-#[derive(Clone, Copy, SchemaRead)]
+#[derive(Clone, Copy, Debug, PartialEq, SchemaRead, SchemaWrite)]
 pub struct WincodeEscrow {
+    pub discriminator: [u8; DISCRIMINATOR_LEN],
     pub maker: [u8; 32],
     pub mint_a: [u8; 32],
     pub mint_b: [u8; 32],
@@ -12,17 +17,37 @@ pub struct WincodeEscrow {
     pub bump: u8,
 }
 impl WincodeEscrow {
-    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 1;
-    pub fn from_account_info(account_info: &AccountView) -> Result<&mut Self, ProgramError> {
-        let mut data = account_info.try_borrow_mut()?;
-        if data.len() != WincodeEscrow::LEN {
+    pub const LEN: usize = DISCRIMINATOR_LEN + 32 + 32 + 32 + 64 + 64 + 1;
+
+    /// Owned copy of the escrow in `data`, for clients reading fetched accounts.
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != WincodeEscrow::LEN
+            || AccountType::from_data(data)? != AccountType::WincodeEscrow
+        {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        if (data.as_ptr() as usize) % core::mem::align_of::<Self>() != 0 {
-            return Err(ProgramError::InvalidAccountData);
+        wincode::deserialize::<WincodeEscrow>(data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// [`WincodeEscrow::try_from_bytes`] for an account that has to be owned by this program.
+    pub fn try_from_account(owner: &Address, data: &[u8]) -> Result<Self, ProgramError> {
+        if *owner != crate::ID {
+            return Err(ProgramError::IllegalOwner);
         }
 
-        Ok(unsafe { &mut *(data.as_mut_ptr() as *mut Self) })
+        Self::try_from_bytes(data)
+    }
+
+    /// The make_v2 amounts live in the first 8 bytes of their fields.
+    pub fn amount_to_receive(&self) -> u64 {
+        u64::from_le_bytes(self.amount_to_receive[..8].try_into().unwrap())
+    }
+
+    pub fn amount_to_give(&self) -> u64 {
+        u64::from_le_bytes(self.amount_to_give[..8].try_into().unwrap())
     }
 }
+
+// the allocated space has to cover the whole struct
+const _: () = assert!(WincodeEscrow::LEN == core::mem::size_of::<WincodeEscrow>());
@@ -0,0 +1,125 @@
+//! Test program that makes, takes and refunds escrows through `pinocchio_escrow::cpi`,
+//! with an escrow maker or taker that is one of its PDAs instead of a wallet.
+
+#![allow(unexpected_cfgs)]
+use pinocchio::{
+    address::declare_id,
+    cpi::{Seed, Signer},
+    entrypoint,
+    error::ProgramError,
+    AccountView, Address, ProgramResult,
+};
+use pinocchio_escrow::cpi::{Make, Refund, Take};
+
+entrypoint!(process_instruction);
+
+declare_id!("3oPwyRUHbTWFrQfrFd6AbcE4MsqkkUwQbRwsXLwKNHVd");
+
+/// Seed of the PDA acting as the escrow maker or taker
+pub const AUTHORITY_SEED: &[u8] = b"authority";
+
+pub fn process_instruction(
+    program_id: &Address,
+    accounts: &[AccountView],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    assert_eq!(program_id, &ID);
+
+    // -- layout --//
+    // 0 descriminator, 0 make, 1 refund and 2 take
+    // 1 authority bump
+    // 2.. make only: escrow bump, amount to receive, amount to give
+    let [descriminator, authority_bump, data @ ..] = instruction_data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+
+    let bump = [*authority_bump];
+    let seeds = [Seed::from(AUTHORITY_SEED), Seed::from(&bump)];
+    let signer = Signer::from(&seeds);
+
+    match descriminator {
+        0 => make(accounts, data, signer),
+        1 => refund(accounts, signer),
+        2 => take(accounts, signer),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Accounts: payer, authority, then the escrow Make accounts after its maker, and the
+/// escrow program.
+fn make(accounts: &[AccountView], data: &[u8], signer: Signer) -> ProgramResult {
+    let [payer, authority, escrow, mint_a, mint_b, authority_ata, escrow_ata, system_program, token_program, associated_token_program, _escrow_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if data.len() != 17 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Make {
+        payer,
+        maker: authority,
+        escrow,
+        mint_a,
+        mint_b,
+        maker_ata: authority_ata,
+        escrow_ata,
+        system_program,
+        token_program,
+        associated_token_program,
+        bump: data[0],
+        amount_to_receive: u64::from_le_bytes(data[1..9].try_into().unwrap()),
+        amount_to_give: u64::from_le_bytes(data[9..17].try_into().unwrap()),
+    }
+    .invoke_signed(&[signer])
+}
+
+/// Accounts: the escrow Refund accounts with the authority as the maker, and the escrow
+/// program.
+fn refund(accounts: &[AccountView], signer: Signer) -> ProgramResult {
+    let [authority, escrow, authority_ata, escrow_ata, token_program, system_program, associated_token_program, mint_a, _escrow_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    Refund {
+        maker: authority,
+        escrow,
+        maker_ata: authority_ata,
+        escrow_ata,
+        token_program,
+        system_program,
+        associated_token_program,
+        mint_a,
+    }
+    .invoke_signed(&[signer])
+}
+
+/// Accounts: the escrow Take accounts with the authority as the taker, and the escrow
+/// program.
+fn take(accounts: &[AccountView], signer: Signer) -> ProgramResult {
+    let [authority, maker, escrow, mint_a, mint_b, authority_ata_a, authority_ata_b, escrow_ata, maker_ata_b, token_program, system_program, associated_token_program, _escrow_program @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    Take {
+        taker: authority,
+        maker,
+        escrow,
+        mint_a,
+        mint_b,
+        taker_ata_a: authority_ata_a,
+        taker_ata_b: authority_ata_b,
+        escrow_ata,
+        maker_ata_b,
+        token_program,
+        system_program,
+        associated_token_program,
+    }
+    .invoke_signed(&[signer])
+}
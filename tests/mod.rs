@@ -2,39 +2,41 @@ pub mod setup;
 
 pub use setup::*;
 
+use pinocchio_escrow::instructions::{
+    htlc::MakeHtlcInstructionData, wincode_v2, EscrowInstruction, MakeArgs, MakeInstructionData,
+    MakeOptions,
+};
 use solana_sdk::message::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signer::Signer;
 
 pub fn make_instruction(ctx: &mut TestContext, v2: bool) {
     let amount_to_receive: u64 = 100_000_000;
     let amount_to_give: u64 = 500_000_000;
-    let amount_to_receive_bytes: [u8; 64] = {
-        let mut arr = [0u8; 64];
-        arr[..8].copy_from_slice(&amount_to_receive.to_le_bytes());
-        arr
-    };
-
-    let amount_to_give_bytes: [u8; 64] = {
-        let mut arr = [0u8; 64];
-        arr[..8].copy_from_slice(&amount_to_give.to_le_bytes());
-        arr
-    };
 
-    let make_data = [
-        if v2 { vec![3u8] } else { vec![0u8] }, // Make discriminator
-        ctx.escrow_bump.to_le_bytes().to_vec(),
-        if v2 {
-            amount_to_receive_bytes.to_vec()
-        } else {
-            amount_to_receive.to_le_bytes().to_vec()
-        },
-        if v2 {
-            amount_to_give_bytes.to_vec()
-        } else {
-            amount_to_give.to_le_bytes().to_vec()
-        },
-    ]
-    .concat();
+    let make_data = if v2 {
+        let widen = |amount: u64| {
+            let mut field = [0u8; 64];
+            field[..8].copy_from_slice(&amount.to_le_bytes());
+            field
+        };
+        EscrowInstruction::MakeV2(wincode_v2::make::MakeInstructionData {
+            bump: ctx.escrow_bump,
+            amount_to_receive: widen(amount_to_receive),
+            amount_to_give: widen(amount_to_give),
+        })
+    } else {
+        EscrowInstruction::Make(MakeArgs {
+            data: MakeInstructionData {
+                bump: ctx.escrow_bump,
+                amount_to_receive,
+                amount_to_give,
+            },
+            options: None,
+            approvers: &[],
+        })
+    }
+    .pack();
 
     let make_ix = Instruction {
         program_id: program_id(),
@@ -59,10 +61,106 @@ pub fn make_instruction(ctx: &mut TestContext, v2: bool) {
     println!("Make transaction Succeeded");
 }
 
-pub fn take_instruction(ctx: &mut TestContext) {
-    let take_data = vec![1u8]; // Take discriminator
+/// v1 Make with the optional fields and approvers appended after the amounts
+pub fn make_with_options_ix(
+    ctx: &TestContext,
+    options: Option<MakeOptions>,
+    approvers: &[Pubkey],
+) -> Instruction {
+    let approvers: Vec<u8> = approvers.iter().flat_map(|a| a.to_bytes()).collect();
+    let make_data = EscrowInstruction::Make(MakeArgs {
+        data: MakeInstructionData {
+            bump: ctx.escrow_bump,
+            amount_to_receive: 100_000_000,
+            amount_to_give: 500_000_000,
+        },
+        options,
+        approvers: &approvers,
+    })
+    .pack();
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.maker.pubkey(), true),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new(ctx.mint_a, false),
+            AccountMeta::new(ctx.mint_b, false),
+            AccountMeta::new(ctx.maker_ata_a, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new(ctx.system_program, false),
+            AccountMeta::new(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(ctx.associated_token_program, false),
+        ],
+        data: make_data,
+    }
+}
+
+pub fn make_with_options_instruction(ctx: &mut TestContext, options: MakeOptions) {
+    let make_ix = make_with_options_ix(ctx, Some(options), &[]);
+
+    let maker_pubkey = ctx.maker.pubkey();
+
+    send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+    println!("Make with options transaction Succeeded");
+}
 
-    let take_ix = Instruction {
+pub fn make_vesting_instruction(ctx: &mut TestContext, cliff: u64, duration: u64) {
+    let options = MakeOptions {
+        vesting_cliff: cliff,
+        vesting_duration: duration,
+        ..Default::default()
+    };
+    make_with_options_instruction(ctx, options);
+}
+
+pub fn make_arbitrated_instruction(ctx: &mut TestContext) {
+    let options = MakeOptions {
+        arbiter: ctx.arbiter.pubkey().to_bytes(),
+        ..Default::default()
+    };
+    make_with_options_instruction(ctx, options);
+}
+
+/// Make requiring `threshold` of `approvers`, returns the approvals PDA
+pub fn make_with_approvals_instruction(
+    ctx: &mut TestContext,
+    threshold: u8,
+    approvers: &[Pubkey],
+) -> Pubkey {
+    let (approvals, approvals_bump) =
+        Pubkey::find_program_address(&[b"approvals".as_ref(), ctx.escrow.as_ref()], &program_id());
+
+    let options = MakeOptions {
+        approval_threshold: threshold,
+        approvals_bump,
+        ..Default::default()
+    };
+
+    let mut make_ix = make_with_options_ix(ctx, Some(options), approvers);
+    make_ix.accounts.push(AccountMeta::new(approvals, false));
+
+    let maker_pubkey = ctx.maker.pubkey();
+    send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+    println!("Make with approvals transaction Succeeded");
+
+    approvals
+}
+
+pub fn approve_ix(ctx: &TestContext, approver: &Pubkey, approvals: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*approver, true),
+            AccountMeta::new_readonly(ctx.escrow, false),
+            AccountMeta::new(*approvals, false),
+        ],
+        data: EscrowInstruction::Approve.pack(),
+    }
+}
+
+pub fn take_ix(ctx: &TestContext) -> Instruction {
+    Instruction {
         program_id: program_id(),
         accounts: vec![
             AccountMeta::new(ctx.taker.pubkey(), true),
@@ -78,18 +176,32 @@ pub fn take_instruction(ctx: &mut TestContext) {
             AccountMeta::new(ctx.system_program, false),
             AccountMeta::new(ctx.associated_token_program, false),
         ],
-        data: take_data,
-    };
+        data: EscrowInstruction::Take.pack(),
+    }
+}
+
+pub fn take_instruction(ctx: &mut TestContext) {
+    let take_ix = take_ix(ctx);
 
     let taker_pubkey = ctx.taker.pubkey();
     send_transaction(&mut ctx.svm, take_ix, &[&ctx.taker], &taker_pubkey);
     println!("Take transaction Succeeded");
 }
 
-pub fn refund_instruction(ctx: &mut TestContext) {
-    let refund_data = vec![2u8];
+pub fn take_arbitrated_instruction(ctx: &mut TestContext) {
+    let mut take_ix = take_ix(ctx);
+    // payment is held in the escrow's mint_b vault
+    take_ix
+        .accounts
+        .push(AccountMeta::new(ctx.escrow_ata_b, false));
+
+    let taker_pubkey = ctx.taker.pubkey();
+    send_transaction(&mut ctx.svm, take_ix, &[&ctx.taker], &taker_pubkey);
+    println!("Take arbitrated transaction Succeeded");
+}
 
-    let refund_ix = Instruction {
+pub fn refund_ix(ctx: &TestContext) -> Instruction {
+    Instruction {
         program_id: program_id(),
         accounts: vec![
             AccountMeta::new(ctx.maker.pubkey(), true),
@@ -100,8 +212,12 @@ pub fn refund_instruction(ctx: &mut TestContext) {
             AccountMeta::new(ctx.system_program, false),
             AccountMeta::new(ctx.associated_token_program, false),
         ],
-        data: refund_data,
-    };
+        data: EscrowInstruction::Refund.pack(),
+    }
+}
+
+pub fn refund_instruction(ctx: &mut TestContext) {
+    let refund_ix = refund_ix(ctx);
 
     let maker_pubkey = ctx.maker.pubkey();
 
@@ -109,27 +225,2163 @@ pub fn refund_instruction(ctx: &mut TestContext) {
     println!("Refund transaction Succeeded");
 }
 
+pub fn claim_ix(ctx: &TestContext) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.taker.pubkey(), true),
+            AccountMeta::new(ctx.maker.pubkey(), false),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new(ctx.taker_ata_a, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new(TOKEN_PROGRAM_ID, false),
+        ],
+        data: EscrowInstruction::Claim.pack(),
+    }
+}
+
+pub fn claim_instruction(ctx: &mut TestContext) {
+    let claim_ix = claim_ix(ctx);
+
+    let taker_pubkey = ctx.taker.pubkey();
+    send_transaction(&mut ctx.svm, claim_ix, &[&ctx.taker], &taker_pubkey);
+    println!("Claim transaction Succeeded");
+}
+
+/// Release or Reverse signed by the arbiter only
+pub fn arbitrate_ix(ctx: &TestContext, release: bool) -> Instruction {
+    let (maker_ata, taker_ata) = if release {
+        (ctx.maker_ata_b, ctx.taker_ata_a)
+    } else {
+        (ctx.maker_ata_a, ctx.taker_ata_b)
+    };
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.maker.pubkey(), false),
+            AccountMeta::new(ctx.taker.pubkey(), false),
+            AccountMeta::new_readonly(ctx.arbiter.pubkey(), true),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new(ctx.escrow_ata_b, false),
+            AccountMeta::new(maker_ata, false),
+            AccountMeta::new(taker_ata, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: if release {
+            EscrowInstruction::Release
+        } else {
+            EscrowInstruction::Reverse
+        }
+        .pack(),
+    }
+}
+
+/// MakeHtlc locking the default amount for `recipient` until `timeout`
+pub fn make_htlc_instruction(
+    ctx: &mut TestContext,
+    preimage: &[u8],
+    timeout: i64,
+    recipient: &Pubkey,
+) {
+    let amount_to_give: u64 = 500_000_000;
+
+    let make_data = EscrowInstruction::MakeHtlc(MakeHtlcInstructionData {
+        bump: ctx.escrow_bump,
+        amount_to_give,
+        hashlock: solana_sha256_hasher::hash(preimage).to_bytes(),
+        timeout,
+        recipient: recipient.to_bytes(),
+    })
+    .pack();
+
+    let make_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.maker.pubkey(), true),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new_readonly(ctx.mint_a, false),
+            AccountMeta::new(ctx.maker_ata_a, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ctx.associated_token_program, false),
+        ],
+        data: make_data,
+    };
+
+    let maker_pubkey = ctx.maker.pubkey();
+    send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+    println!("Make HTLC transaction Succeeded");
+}
+
+pub fn redeem_ix(ctx: &TestContext, preimage: &[u8], recipient_ata_a: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.maker.pubkey(), false),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new(*recipient_ata_a, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: EscrowInstruction::Redeem { preimage }.pack(),
+    }
+}
+
+/// Builds `name` the way an IDL client would: the IDL discriminator, the IDL accounts looked
+/// up in `accounts` by name, then each IDL arg from `args` encoded by its IDL type
+pub fn idl_ix(name: &str, accounts: &[(&str, Pubkey)], args: serde_json::Value) -> Instruction {
+    let idl = pinocchio_escrow::idl::idl();
+    let ix = idl["instructions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|ix| ix["name"] == name)
+        .unwrap_or_else(|| panic!("{name} is not in the IDL"));
+    let bytes = |value: &serde_json::Value| -> Vec<u8> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b.as_u64().unwrap() as u8)
+            .collect()
+    };
+
+    let mut data = bytes(&ix["discriminator"]);
+    for arg in ix["args"].as_array().unwrap() {
+        let value = &args[arg["name"].as_str().unwrap()];
+        // optional args end the data when they are left out
+        if value.is_null() && arg.get("docs").is_some() {
+            break;
+        }
+        if let Some(ty) = arg["type"].get("trailing") {
+            assert_eq!(ty, "pubkey");
+            for pubkey in value.as_array().unwrap() {
+                let pubkey: Pubkey = pubkey.as_str().unwrap().parse().unwrap();
+                data.extend_from_slice(pubkey.as_ref());
+            }
+            continue;
+        }
+        match arg["type"].as_str() {
+            Some("u8") => data.push(value.as_u64().unwrap() as u8),
+            Some("u16") => data.extend_from_slice(&(value.as_u64().unwrap() as u16).to_le_bytes()),
+            Some("u64") => data.extend_from_slice(&value.as_u64().unwrap().to_le_bytes()),
+            Some("i64") => data.extend_from_slice(&value.as_i64().unwrap().to_le_bytes()),
+            Some("pubkey") => {
+                let pubkey: Pubkey = value.as_str().unwrap().parse().unwrap();
+                data.extend_from_slice(pubkey.as_ref());
+            }
+            Some("bytes") => {
+                let value = bytes(value);
+                data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                data.extend_from_slice(&value);
+            }
+            _ => data.extend_from_slice(&bytes(value)),
+        }
+    }
+
+    let meta = |account: &serde_json::Value, pubkey: &Pubkey| AccountMeta {
+        pubkey: *pubkey,
+        is_signer: account["signer"] == true,
+        is_writable: account["writable"] == true,
+    };
+    let mut metas: Vec<AccountMeta> = ix["accounts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|account| {
+            let account_name = account["name"].as_str().unwrap();
+            let (_, pubkey) = accounts
+                .iter()
+                .find(|(name, _)| *name == account_name)
+                .unwrap_or_else(|| panic!("{account_name} is missing"));
+            meta(account, pubkey)
+        })
+        .collect();
+    // remaining accounts are passed when given, repeated ones as often as they are given
+    if let Some(remaining) = ix["remaining_accounts"].as_array() {
+        for account in remaining {
+            metas.extend(
+                accounts
+                    .iter()
+                    .filter(|(name, _)| account["name"] == *name)
+                    .map(|(_, pubkey)| meta(account, pubkey)),
+            );
+        }
+    }
+
+    Instruction {
+        program_id: program_id(),
+        accounts: metas,
+        data,
+    }
+}
+
+/// v1 Make for an extra maker, offering mint_a for mint_b when `give_a` and the opposite otherwise
+pub fn make_for_ix(
+    ctx: &TestContext,
+    maker: &ExtraMaker,
+    give_a: bool,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) -> Instruction {
+    let (mint_give, mint_receive, maker_ata, escrow_ata) = if give_a {
+        (ctx.mint_a, ctx.mint_b, maker.ata_a, maker.escrow_ata_a)
+    } else {
+        (ctx.mint_b, ctx.mint_a, maker.ata_b, maker.escrow_ata_b)
+    };
+
+    let make_data = EscrowInstruction::Make(MakeArgs {
+        data: MakeInstructionData {
+            bump: maker.escrow_bump,
+            amount_to_receive,
+            amount_to_give,
+        },
+        options: None,
+        approvers: &[],
+    })
+    .pack();
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(maker.keypair.pubkey(), true),
+            AccountMeta::new(maker.escrow, false),
+            AccountMeta::new_readonly(mint_give, false),
+            AccountMeta::new_readonly(mint_receive, false),
+            AccountMeta::new(maker_ata, false),
+            AccountMeta::new(escrow_ata, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ctx.associated_token_program, false),
+        ],
+        data: make_data,
+    }
+}
+
+pub fn make_for_instruction(
+    ctx: &mut TestContext,
+    maker: &ExtraMaker,
+    give_a: bool,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) {
+    let make_ix = make_for_ix(ctx, maker, give_a, amount_to_receive, amount_to_give);
+
+    let maker_pubkey = maker.keypair.pubkey();
+    send_transaction(&mut ctx.svm, make_ix, &[&maker.keypair], &maker_pubkey);
+    println!("Make for extra maker transaction Succeeded");
+}
+
+pub fn take_many_ix(ctx: &TestContext, makers: &[&ExtraMaker]) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(ctx.taker.pubkey(), true),
+        AccountMeta::new_readonly(ctx.mint_a, false),
+        AccountMeta::new_readonly(ctx.mint_b, false),
+        AccountMeta::new(ctx.taker_ata_a, false),
+        AccountMeta::new(ctx.taker_ata_b, false),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+    ];
+    for maker in makers {
+        accounts.extend([
+            AccountMeta::new(maker.escrow, false),
+            AccountMeta::new(maker.escrow_ata_a, false),
+            AccountMeta::new(maker.keypair.pubkey(), false),
+            AccountMeta::new(maker.ata_b, false),
+        ]);
+    }
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: EscrowInstruction::TakeMany.pack(),
+    }
+}
+
+/// Match `a` (offering mint_a) against `b` (offering mint_b)
+pub fn match_ix(a: &ExtraMaker, b: &ExtraMaker, keeper_ata_b: Option<&Pubkey>) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(a.escrow, false),
+        AccountMeta::new(a.escrow_ata_a, false),
+        AccountMeta::new(a.keypair.pubkey(), false),
+        AccountMeta::new(a.ata_b, false),
+        AccountMeta::new(b.escrow, false),
+        AccountMeta::new(b.escrow_ata_b, false),
+        AccountMeta::new(b.keypair.pubkey(), false),
+        AccountMeta::new(b.ata_a, false),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+    ];
+    if let Some(keeper_ata_b) = keeper_ata_b {
+        accounts.push(AccountMeta::new(*keeper_ata_b, false));
+    }
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: EscrowInstruction::Match.pack(),
+    }
+}
+
+/// InitMarket for (mint_a, mint_b) paid by the taker, returns the market PDA
+pub fn init_market_instruction(ctx: &mut TestContext) -> Pubkey {
+    let (market, market_bump) = Pubkey::find_program_address(
+        &[b"market".as_ref(), ctx.mint_a.as_ref(), ctx.mint_b.as_ref()],
+        &program_id(),
+    );
+
+    let init_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(ctx.taker.pubkey(), true),
+            AccountMeta::new(market, false),
+            AccountMeta::new_readonly(ctx.mint_a, false),
+            AccountMeta::new_readonly(ctx.mint_b, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+        ],
+        data: EscrowInstruction::InitMarket { bump: market_bump }.pack(),
+    };
+
+    let taker_pubkey = ctx.taker.pubkey();
+    send_transaction(&mut ctx.svm, init_ix, &[&ctx.taker], &taker_pubkey);
+    println!("Init market transaction Succeeded");
+
+    market
+}
+
+/// Escrow addresses of the `n` best offers in `market`
+pub fn best_offers(ctx: &mut TestContext, market: &Pubkey, n: u8) -> Vec<Pubkey> {
+    best_offers_entries(ctx, market, n)
+        .into_iter()
+        .map(|(escrow, _, _)| escrow)
+        .collect()
+}
+
+/// Escrow, amount to give and amount to receive of the `n` best offers in `market`
+pub fn best_offers_entries(
+    ctx: &mut TestContext,
+    market: &Pubkey,
+    n: u8,
+) -> Vec<(Pubkey, u64, u64)> {
+    let ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![AccountMeta::new_readonly(*market, false)],
+        data: EscrowInstruction::BestOffers { n }.pack(),
+    };
+
+    let taker_pubkey = ctx.taker.pubkey();
+    let meta = try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey)
+        .expect("BestOffers should succeed");
+    ctx.svm.expire_blockhash();
+
+    meta.return_data
+        .data
+        .chunks_exact(48)
+        .map(|entry| {
+            (
+                Pubkey::try_from(&entry[..32]).unwrap(),
+                u64::from_le_bytes(entry[32..40].try_into().unwrap()),
+                u64::from_le_bytes(entry[40..48].try_into().unwrap()),
+            )
+        })
+        .collect()
+}
+
+pub fn set_delegate_instruction(ctx: &mut TestContext, delegate: &Pubkey) {
+    let set_delegate_ix = Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new_readonly(ctx.maker.pubkey(), true),
+            AccountMeta::new(ctx.escrow, false),
+        ],
+        data: EscrowInstruction::SetDelegate {
+            delegate: delegate.to_bytes(),
+        }
+        .pack(),
+    };
+
+    let maker_pubkey = ctx.maker.pubkey();
+    send_transaction(&mut ctx.svm, set_delegate_ix, &[&ctx.maker], &maker_pubkey);
+    println!("Set delegate transaction Succeeded");
+}
+
+/// Refund signed by `delegate` instead of the maker
+pub fn refund_by_ix(ctx: &TestContext, delegate: &Pubkey) -> Instruction {
+    let mut refund_ix = refund_ix(ctx);
+    refund_ix.accounts[0].is_signer = false;
+    refund_ix
+        .accounts
+        .push(AccountMeta::new_readonly(*delegate, true));
+    refund_ix
+}
+
+/// TakeFor paid by the taker, sending mint_a to `recipient_ata_a` owned by `recipient`
+pub fn take_for_ix(ctx: &TestContext, recipient: &Pubkey, recipient_ata_a: &Pubkey) -> Instruction {
+    let mut take_ix = take_ix(ctx);
+    take_ix.data = EscrowInstruction::TakeFor.pack();
+    take_ix.accounts[5] = AccountMeta::new(*recipient_ata_a, false);
+    take_ix
+        .accounts
+        .insert(0, AccountMeta::new_readonly(*recipient, false));
+    take_ix
+}
+
+/// Make through the CPI harness, with its authority PDA as the maker and `payer`
+/// covering the rent
+pub fn harness_make_ix(
+    ctx: &TestContext,
+    harness: &HarnessMaker,
+    payer: &Pubkey,
+    amount_to_receive: u64,
+    amount_to_give: u64,
+) -> Instruction {
+    let mut data = vec![0, harness.authority_bump, harness.escrow_bump];
+    data.extend_from_slice(&amount_to_receive.to_le_bytes());
+    data.extend_from_slice(&amount_to_give.to_le_bytes());
+
+    Instruction {
+        program_id: harness.program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(harness.authority, false),
+            AccountMeta::new(harness.escrow, false),
+            AccountMeta::new_readonly(ctx.mint_a, false),
+            AccountMeta::new_readonly(ctx.mint_b, false),
+            AccountMeta::new(harness.authority_ata_a, false),
+            AccountMeta::new(harness.escrow_ata_a, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ctx.associated_token_program, false),
+            AccountMeta::new_readonly(program_id(), false),
+        ],
+        data,
+    }
+}
+
+/// Refund through the CPI harness, signed by its authority PDA
+pub fn harness_refund_ix(ctx: &TestContext, harness: &HarnessMaker) -> Instruction {
+    Instruction {
+        program_id: harness.program_id,
+        accounts: vec![
+            AccountMeta::new(harness.authority, false),
+            AccountMeta::new(harness.escrow, false),
+            AccountMeta::new(harness.authority_ata_a, false),
+            AccountMeta::new(harness.escrow_ata_a, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+            AccountMeta::new_readonly(ctx.associated_token_program, false),
+            AccountMeta::new_readonly(ctx.mint_a, false),
+            AccountMeta::new_readonly(program_id(), false),
+        ],
+        data: vec![1, harness.authority_bump],
+    }
+}
+
+/// Take of the test context's escrow through the CPI harness, its authority PDA is the taker
+pub fn harness_take_ix(ctx: &TestContext, harness: &HarnessMaker) -> Instruction {
+    Instruction {
+        program_id: harness.program_id,
+        accounts: vec![
+            AccountMeta::new(harness.authority, false),
+            AccountMeta::new(ctx.maker.pubkey(), false),
+            AccountMeta::new(ctx.escrow, false),
+            AccountMeta::new_readonly(ctx.mint_a, false),
+            AccountMeta::new_readonly(ctx.mint_b, false),
+            AccountMeta::new(harness.authority_ata_a, false),
+            AccountMeta::new(harness.authority_ata_b, false),
+            AccountMeta::new(ctx.escrow_ata_a, false),
+            AccountMeta::new(ctx.maker_ata_b, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(ctx.system_program, false),
+            AccountMeta::new_readonly(ctx.associated_token_program, false),
+            AccountMeta::new_readonly(program_id(), false),
+        ],
+        data: vec![2, harness.authority_bump],
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{make_instruction, refund_instruction, setup, take_instruction};
+    use crate::{
+        add_harness_maker, add_maker, approve_ix, arbitrate_ix, best_offers, best_offers_entries,
+        claim_instruction, claim_ix, escrow_events, harness_make_ix, harness_refund_ix,
+        harness_take_ix, idl_ix, init_market_instruction, make_arbitrated_instruction,
+        make_for_instruction, make_for_ix, make_htlc_instruction, make_instruction,
+        make_vesting_instruction, make_with_approvals_instruction, make_with_options_ix, match_ix,
+        now, program_id, redeem_ix, refund_by_ix, refund_instruction, refund_ix, send_transaction,
+        set_delegate_instruction, setup, take_arbitrated_instruction, take_for_ix,
+        take_instruction, take_ix, take_many_ix, token_balance, try_send_transaction, warp_clock,
+        ExtraMaker, TestContext, TOKEN_PROGRAM_ID,
+    };
+    use litesvm_token::{spl_token, CreateAssociatedTokenAccount, CreateMint, MintTo};
+    use pinocchio_escrow::{
+        client,
+        events::{EscrowEvent, EscrowEventKind},
+        idl,
+        instructions::{
+            htlc::MakeHtlcInstructionData, wincode_v2, EscrowInstrctions, EscrowInstruction,
+            MakeArgs, MakeInstructionData, MakeOptions,
+        },
+        state::{
+            escrow_extensions, get_extension, AccountType, Approvals, Escrow, ExtensionType,
+            Market, MarketEntry, WincodeEscrow, DISCRIMINATOR_LEN, MARKET_CAPACITY, MAX_APPROVERS,
+        },
+    };
+    use solana_sdk::{
+        instruction::InstructionError,
+        message::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        transaction::TransactionError,
+    };
 
     #[test]
     pub fn test_make_instruction() {
         let mut ctx = setup();
-        make_instruction(&mut ctx,false);
+        make_instruction(&mut ctx, false);
+    }
+
+    #[test]
+    pub fn test_make_v2_instruction() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+    }
+
+    #[test]
+    pub fn test_make_rejects_malformed_data() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+
+        // truncated before the end of the amounts
+        for len in [1, 2, 10, 17] {
+            let mut ix = make_with_options_ix(&ctx, None, &[]);
+            ix.data.truncate(len);
+            assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+        }
+
+        // options cut off inside a field
+        for tail in [1, 8, 20, 40, 49] {
+            let mut ix = make_with_options_ix(&ctx, Some(MakeOptions::default()), &[]);
+            ix.data.truncate(1 + MakeInstructionData::LEN + tail);
+            assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+        }
+
+        // approvers without a threshold, and more of them than fit the approvals account
+        let approvers: Vec<Pubkey> = (0..=MAX_APPROVERS as u8)
+            .map(|i| Pubkey::new_from_array([i + 1; 32]))
+            .collect();
+        let ix = make_with_options_ix(&ctx, Some(MakeOptions::default()), &approvers[..1]);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+
+        let options = MakeOptions {
+            approval_threshold: 1,
+            ..Default::default()
+        };
+        let ix = make_with_options_ix(&ctx, Some(options), &approvers);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+
+        // none of the rejected payloads created the escrow
+        assert!(ctx.svm.get_account(&ctx.escrow).is_none());
+        make_instruction(&mut ctx, false);
     }
 
     #[test]
     pub fn test_take_instruction() {
         let mut ctx = setup();
-        make_instruction(&mut ctx,true);
+        make_instruction(&mut ctx, true);
         take_instruction(&mut ctx);
     }
 
     #[test]
     pub fn test_refund_instruction() {
         let mut ctx = setup();
-        make_instruction(&mut ctx,true);
+        make_instruction(&mut ctx, true);
         refund_instruction(&mut ctx);
     }
+
+    #[test]
+    pub fn test_vesting_claim() {
+        let mut ctx = setup();
+        make_vesting_instruction(&mut ctx, 100, 1_000);
+        take_instruction(&mut ctx);
+
+        // payment settles immediately, mint_a stays locked
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 0);
+
+        // nothing is claimable before the cliff
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = claim_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        warp_clock(&mut ctx.svm, 250);
+        claim_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 125_000_000);
+
+        warp_clock(&mut ctx.svm, 1_000);
+        claim_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_refund_after_vesting_take_fails() {
+        let mut ctx = setup();
+        make_vesting_instruction(&mut ctx, 0, 1_000);
+        take_instruction(&mut ctx);
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let ix = refund_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+    }
+
+    #[test]
+    pub fn test_arbiter_release() {
+        let mut ctx = setup();
+        make_arbitrated_instruction(&mut ctx);
+
+        // the plain Take would skip the held vault
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        take_arbitrated_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.escrow_ata_b), 100_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 0);
+
+        let ix = arbitrate_ix(&ctx, true);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker, &ctx.arbiter], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_arbiter_reverse() {
+        let mut ctx = setup();
+        make_arbitrated_instruction(&mut ctx);
+        take_arbitrated_instruction(&mut ctx);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = arbitrate_ix(&ctx, false);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker, &ctx.arbiter], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_b), 1_000_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+    }
+
+    #[test]
+    pub fn test_take_with_approvals() {
+        let mut ctx = setup();
+        let approvers = [Keypair::new(), Keypair::new(), Keypair::new()];
+        let approver_keys: Vec<_> = approvers.iter().map(|a| a.pubkey()).collect();
+        let approvals = make_with_approvals_instruction(&mut ctx, 2, &approver_keys);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let mut take = take_ix(&ctx);
+        take.accounts.push(AccountMeta::new(approvals, false));
+
+        // no approvals yet
+        let ix = take.clone();
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        // one recorded approval plus one approver co-signing the Take
+        let ix = approve_ix(&ctx, &approver_keys[0], &approvals);
+        send_transaction(
+            &mut ctx.svm,
+            ix,
+            &[&ctx.taker, &approvers[0]],
+            &taker_pubkey,
+        );
+
+        take.accounts
+            .push(AccountMeta::new_readonly(approver_keys[2], true));
+        send_transaction(
+            &mut ctx.svm,
+            take,
+            &[&ctx.taker, &approvers[2]],
+            &taker_pubkey,
+        );
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+
+        // the approvals PDA is closed by the take, so late sign-offs are rejected
+        assert!(ctx.svm.get_account(&approvals).is_none());
+        let ix = approve_ix(&ctx, &approver_keys[1], &approvals);
+        assert!(try_send_transaction(
+            &mut ctx.svm,
+            ix,
+            &[&ctx.taker, &approvers[1]],
+            &taker_pubkey
+        )
+        .is_err());
+    }
+
+    #[test]
+    pub fn test_refund_closes_approvals() {
+        let mut ctx = setup();
+        let approvers = [Keypair::new(), Keypair::new()];
+        let approver_keys: Vec<_> = approvers.iter().map(|a| a.pubkey()).collect();
+        let approvals = make_with_approvals_instruction(&mut ctx, 1, &approver_keys);
+        let approvals_rent = ctx.svm.get_account(&approvals).unwrap().lamports;
+
+        let maker_pubkey = ctx.maker.pubkey();
+        // the approvals PDA has to come along
+        let ix = refund_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+
+        let mut refund = refund_ix(&ctx);
+        refund.accounts.push(AccountMeta::new(approvals, false));
+        let before = ctx.svm.get_account(&maker_pubkey).unwrap().lamports;
+        send_transaction(&mut ctx.svm, refund, &[&ctx.maker], &maker_pubkey);
+
+        assert!(ctx.svm.get_account(&approvals).is_none());
+        // the maker pays the fee but gets the approvals and vault rent back
+        let after = ctx.svm.get_account(&maker_pubkey).unwrap().lamports;
+        assert!(after > before + approvals_rent - 10_000);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = approve_ix(&ctx, &approver_keys[0], &approvals);
+        assert!(try_send_transaction(
+            &mut ctx.svm,
+            ix,
+            &[&ctx.taker, &approvers[0]],
+            &taker_pubkey
+        )
+        .is_err());
+    }
+
+    #[test]
+    pub fn test_htlc_redeem() {
+        let mut ctx = setup();
+        let taker_pubkey = ctx.taker.pubkey();
+        let timeout = now(&ctx.svm) + 1_000;
+        make_htlc_instruction(&mut ctx, b"open sesame", timeout, &taker_pubkey);
+
+        // a plain Take can't bypass the hashlock
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        let ix = redeem_ix(&ctx, b"wrong guess", &ctx.taker_ata_a);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        // anyone can relay the preimage, here the maker pays the fee
+        let maker_pubkey = ctx.maker.pubkey();
+        let ix = redeem_ix(&ctx, b"open sesame", &ctx.taker_ata_a);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_idl_encoded_htlc_redeem() {
+        let mut ctx = setup();
+        let taker_pubkey = ctx.taker.pubkey();
+        let maker_pubkey = ctx.maker.pubkey();
+        let preimage = b"open sesame";
+
+        let ix = idl_ix(
+            "make_htlc",
+            &[
+                ("maker", maker_pubkey),
+                ("escrow", ctx.escrow),
+                ("mint_a", ctx.mint_a),
+                ("maker_ata", ctx.maker_ata_a),
+                ("escrow_ata", ctx.escrow_ata_a),
+                ("system_program", ctx.system_program),
+                ("token_program", TOKEN_PROGRAM_ID),
+                ("associated_token_program", ctx.associated_token_program),
+            ],
+            serde_json::json!({
+                "bump": ctx.escrow_bump,
+                "amount_to_give": 500_000_000u64,
+                "hashlock": solana_sha256_hasher::hash(preimage).to_bytes(),
+                "timeout": now(&ctx.svm) + 1_000,
+                "recipient": taker_pubkey.to_string(),
+            }),
+        );
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+
+        let ix = idl_ix(
+            "redeem",
+            &[
+                ("maker", maker_pubkey),
+                ("escrow", ctx.escrow),
+                ("recipient_ata_a", ctx.taker_ata_a),
+                ("escrow_ata_a", ctx.escrow_ata_a),
+                ("token_program", TOKEN_PROGRAM_ID),
+            ],
+            serde_json::json!({ "preimage": preimage }),
+        );
+        // same payload as the codec writes, after either discriminator
+        assert!(ix
+            .data
+            .ends_with(&EscrowInstruction::Redeem { preimage }.pack()[1..]));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_htlc_refund_after_timeout() {
+        let mut ctx = setup();
+        let taker_pubkey = ctx.taker.pubkey();
+        let timeout = now(&ctx.svm) + 1_000;
+        make_htlc_instruction(&mut ctx, b"open sesame", timeout, &taker_pubkey);
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let ix = refund_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+
+        warp_clock(&mut ctx.svm, 1_000);
+        let ix = redeem_ix(&ctx, b"open sesame", &ctx.taker_ata_a);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        refund_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+    }
+
+    #[test]
+    pub fn test_take_many() {
+        let mut ctx = setup();
+        let first = add_maker(&mut ctx);
+        let second = add_maker(&mut ctx);
+        make_for_instruction(&mut ctx, &first, true, 100_000_000, 200_000_000);
+        make_for_instruction(&mut ctx, &second, true, 50_000_000, 300_000_000);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_many_ix(&ctx, &[&first, &second]);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &first.ata_b), 1_100_000_000);
+        assert_eq!(token_balance(&ctx.svm, &second.ata_b), 1_050_000_000);
+    }
+
+    #[test]
+    pub fn test_take_many_is_atomic() {
+        let mut ctx = setup();
+        let first = add_maker(&mut ctx);
+        make_for_instruction(&mut ctx, &first, true, 100_000_000, 200_000_000);
+        make_vesting_instruction(&mut ctx, 0, 1_000);
+
+        // the vesting escrow can't be batch-taken, so the first fill is rolled back too
+        let vesting = ExtraMaker {
+            keypair: ctx.maker.insecure_clone(),
+            ata_a: ctx.maker_ata_a,
+            ata_b: ctx.maker_ata_b,
+            escrow: ctx.escrow,
+            escrow_bump: ctx.escrow_bump,
+            escrow_ata_a: ctx.escrow_ata_a,
+            escrow_ata_b: ctx.escrow_ata_b,
+        };
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_many_ix(&ctx, &[&first, &vesting]);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 0);
+        assert_eq!(token_balance(&ctx.svm, &first.escrow_ata_a), 200_000_000);
+    }
+
+    #[test]
+    pub fn test_match_full_fill_pays_keeper() {
+        let mut ctx = setup();
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+        // a sells 200 A for 100 B, b buys 200 A for up to 150 B
+        make_for_instruction(&mut ctx, &a, true, 100_000_000, 200_000_000);
+        make_for_instruction(&mut ctx, &b, false, 200_000_000, 150_000_000);
+
+        let keeper_pubkey = ctx.taker.pubkey();
+        let ix = match_ix(&a, &b, Some(&ctx.taker_ata_b));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &keeper_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &a.ata_b), 1_100_000_000);
+        assert_eq!(token_balance(&ctx.svm, &b.ata_a), 1_200_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_b), 1_050_000_000);
+        assert!(ctx.svm.get_account(&a.escrow_ata_a).is_none());
+        assert!(ctx.svm.get_account(&b.escrow_ata_b).is_none());
+    }
+
+    #[test]
+    pub fn test_match_partial_fill() {
+        let mut ctx = setup();
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+        // a sells 400 A for 200 B, b only wants 200 A
+        make_for_instruction(&mut ctx, &a, true, 200_000_000, 400_000_000);
+        make_for_instruction(&mut ctx, &b, false, 200_000_000, 150_000_000);
+
+        // without a keeper account the surplus goes to a
+        let keeper_pubkey = ctx.taker.pubkey();
+        let ix = match_ix(&a, &b, None);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &keeper_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &a.ata_b), 1_150_000_000);
+        assert_eq!(token_balance(&ctx.svm, &a.escrow_ata_a), 200_000_000);
+        assert_eq!(token_balance(&ctx.svm, &b.ata_a), 1_200_000_000);
+    }
+
+    #[test]
+    pub fn test_match_rejects_uncrossed_prices() {
+        let mut ctx = setup();
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+        make_for_instruction(&mut ctx, &a, true, 200_000_000, 200_000_000);
+        make_for_instruction(&mut ctx, &b, false, 200_000_000, 100_000_000);
+
+        let keeper_pubkey = ctx.taker.pubkey();
+        let ix = match_ix(&a, &b, None);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &keeper_pubkey).is_err());
+    }
+
+    #[test]
+    pub fn test_make_rejects_wrong_escrow() {
+        let mut ctx = setup();
+        let other = add_maker(&mut ctx);
+
+        let mut ix = make_with_options_ix(&ctx, None, &[]);
+        ix.accounts[1] = AccountMeta::new(other.escrow, false);
+        let maker_pubkey = ctx.maker.pubkey();
+        let result = try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+        assert_eq!(
+            result.unwrap_err().err,
+            TransactionError::InstructionError(0, InstructionError::InvalidSeeds)
+        );
+    }
+
+    #[test]
+    pub fn test_market_index() {
+        let mut ctx = setup();
+        let market = init_market_instruction(&mut ctx);
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let mut ix = make_with_options_ix(&ctx, None, &[]); // 100 B for 500 A
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+
+        for (maker, amount_to_receive, amount_to_give) in [
+            (&a, 100_000_000, 200_000_000),
+            (&b, 80_000_000, 100_000_000),
+        ] {
+            let mut ix = make_for_ix(&ctx, maker, true, amount_to_receive, amount_to_give);
+            ix.accounts.push(AccountMeta::new(market, false));
+            let maker_pubkey = maker.keypair.pubkey();
+            send_transaction(&mut ctx.svm, ix, &[&maker.keypair], &maker_pubkey);
+        }
+
+        assert_eq!(
+            best_offers(&mut ctx, &market, 2),
+            vec![ctx.escrow, a.escrow]
+        );
+
+        // listed escrows can't be taken without delisting them
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        let mut ix = take_ix(&ctx);
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+
+        assert_eq!(best_offers(&mut ctx, &market, 5), vec![a.escrow, b.escrow]);
+    }
+
+    #[test]
+    pub fn test_init_market_rejects_non_canonical_bump() {
+        let mut ctx = setup();
+        let seeds = [b"market".as_ref(), ctx.mint_a.as_ref(), ctx.mint_b.as_ref()];
+        let (_, canonical) = Pubkey::find_program_address(&seeds, &program_id());
+        let (market, bump) = (0..canonical)
+            .rev()
+            .find_map(|bump| {
+                let bump_seed = [bump];
+                Pubkey::create_program_address(
+                    &[seeds[0], seeds[1], seeds[2], &bump_seed],
+                    &program_id(),
+                )
+                .ok()
+                .map(|market| (market, bump))
+            })
+            .expect("a second valid bump");
+
+        let ix = Instruction {
+            program_id: program_id(),
+            accounts: vec![
+                AccountMeta::new(ctx.taker.pubkey(), true),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(ctx.mint_a, false),
+                AccountMeta::new_readonly(ctx.mint_b, false),
+                AccountMeta::new_readonly(ctx.system_program, false),
+            ],
+            data: EscrowInstruction::InitMarket { bump }.pack(),
+        };
+        let taker_pubkey = ctx.taker.pubkey();
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+        assert!(ctx.svm.get_account(&market).is_none());
+    }
+
+    #[test]
+    pub fn test_full_market_evicts_worst_offer() {
+        let mut ctx = setup();
+        let market = init_market_instruction(&mut ctx);
+
+        let makers: Vec<_> = (0..MARKET_CAPACITY).map(|_| add_maker(&mut ctx)).collect();
+        for (i, maker) in makers.iter().enumerate() {
+            let amount_to_receive = 100_000_000 + i as u64 * 1_000_000;
+            let mut ix = make_for_ix(&ctx, maker, true, amount_to_receive, 100_000_000);
+            ix.accounts.push(AccountMeta::new(market, false));
+            let maker_pubkey = maker.keypair.pubkey();
+            send_transaction(&mut ctx.svm, ix, &[&maker.keypair], &maker_pubkey);
+        }
+
+        // a worse offer can't get in
+        let late = add_maker(&mut ctx);
+        let late_pubkey = late.keypair.pubkey();
+        let mut ix = make_for_ix(&ctx, &late, true, 200_000_000, 100_000_000);
+        ix.accounts.push(AccountMeta::new(market, false));
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&late.keypair], &late_pubkey).is_err());
+
+        // a cheaper one pushes out the worst
+        let mut ix = make_for_ix(&ctx, &late, true, 50_000_000, 100_000_000);
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&late.keypair], &late_pubkey);
+
+        assert_eq!(best_offers(&mut ctx, &market, 1), vec![late.escrow]);
+        // entry count right after the bump, then the slab
+        let market_len =
+            |ctx: &TestContext| ctx.svm.get_account(&market).unwrap().data[DISCRIMINATOR_LEN + 65];
+        let worst = &makers[MARKET_CAPACITY - 1];
+        let data = ctx.svm.get_account(&market).unwrap().data;
+        assert_eq!(market_len(&ctx) as usize, MARKET_CAPACITY);
+        assert!(!data.windows(32).any(|w| w == worst.escrow.as_ref()));
+
+        // the evicted escrow still settles with its market
+        let taker_pubkey = ctx.taker.pubkey();
+        let mut ix = take_many_ix(&ctx, &[worst]);
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 100_000_000);
+        assert_eq!(market_len(&ctx) as usize, MARKET_CAPACITY);
+    }
+
+    #[test]
+    pub fn test_take_many_delists() {
+        let mut ctx = setup();
+        let market = init_market_instruction(&mut ctx);
+        let first = add_maker(&mut ctx);
+        let second = add_maker(&mut ctx);
+        make_for_instruction(&mut ctx, &first, true, 100_000_000, 200_000_000);
+        let mut ix = make_for_ix(&ctx, &second, true, 50_000_000, 300_000_000);
+        ix.accounts.push(AccountMeta::new(market, false));
+        let second_pubkey = second.keypair.pubkey();
+        send_transaction(&mut ctx.svm, ix, &[&second.keypair], &second_pubkey);
+
+        // the listed escrow needs its market to be delisted
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_many_ix(&ctx, &[&first, &second]);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        let mut ix = take_many_ix(&ctx, &[&first, &second]);
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert!(best_offers(&mut ctx, &market, 5).is_empty());
+    }
+
+    #[test]
+    pub fn test_match_listed_escrow() {
+        let mut ctx = setup();
+        let market = init_market_instruction(&mut ctx);
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+        // a sells 400 A for 200 B from the market, b only wants 200 A
+        let mut ix = make_for_ix(&ctx, &a, true, 200_000_000, 400_000_000);
+        ix.accounts.push(AccountMeta::new(market, false));
+        let a_pubkey = a.keypair.pubkey();
+        send_transaction(&mut ctx.svm, ix, &[&a.keypair], &a_pubkey);
+        make_for_instruction(&mut ctx, &b, false, 200_000_000, 150_000_000);
+
+        let keeper_pubkey = ctx.taker.pubkey();
+        let mut ix = match_ix(&a, &b, None);
+        ix.accounts.push(AccountMeta::new(market, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &keeper_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &b.ata_a), 1_200_000_000);
+
+        // the rest of a stays listed at its remaining amounts
+        let offers = best_offers_entries(&mut ctx, &market, 5);
+        assert_eq!(offers, vec![(a.escrow, 200_000_000, 100_000_000)]);
+    }
+
+    #[test]
+    pub fn test_delegate_refund() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        let delegate = Keypair::new();
+        let stranger = Keypair::new();
+        ctx.svm.airdrop(&delegate.pubkey(), 1_000_000_000).unwrap();
+        ctx.svm.airdrop(&stranger.pubkey(), 1_000_000_000).unwrap();
+        set_delegate_instruction(&mut ctx, &delegate.pubkey());
+
+        let ix = refund_by_ix(&ctx, &stranger.pubkey());
+        let stranger_pubkey = stranger.pubkey();
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&stranger], &stranger_pubkey).is_err());
+
+        // the delegate pays the fee, the tokens still go back to the maker
+        let ix = refund_by_ix(&ctx, &delegate.pubkey());
+        let delegate_pubkey = delegate.pubkey();
+        send_transaction(&mut ctx.svm, ix, &[&delegate], &delegate_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+    }
+
+    #[test]
+    pub fn test_take_for_recipient() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        let recipient = Keypair::new().pubkey();
+        let recipient_ata_a =
+            CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.taker, &ctx.mint_a)
+                .owner(&recipient)
+                .send()
+                .unwrap();
+
+        // a plain Take still requires the signer to own the mint_a account
+        let taker_pubkey = ctx.taker.pubkey();
+        let mut ix = take_ix(&ctx);
+        ix.accounts[5] = AccountMeta::new(recipient_ata_a, false);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        let ix = take_for_ix(&ctx, &recipient, &recipient_ata_a);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &recipient_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_b), 900_000_000);
+    }
+
+    #[test]
+    pub fn test_make_with_payer() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = client::make_with_payer_ix(
+            &taker_pubkey,
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+        );
+
+        // the maker still has to sign for the mint_a it gives
+        let mut unsigned = ix.clone();
+        unsigned.accounts[1].is_signer = false;
+        assert!(
+            try_send_transaction(&mut ctx.svm, unsigned, &[&ctx.taker], &taker_pubkey).is_err()
+        );
+
+        let maker_lamports = ctx.svm.get_balance(&maker_pubkey).unwrap();
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker, &ctx.maker], &taker_pubkey);
+        assert_eq!(ctx.svm.get_balance(&maker_pubkey).unwrap(), maker_lamports);
+        assert_eq!(token_balance(&ctx.svm, &ctx.escrow_ata_a), 500_000_000);
+
+        take_instruction(&mut ctx);
+    }
+
+    #[test]
+    pub fn test_cpi_make_and_refund_with_pda_maker() {
+        let mut ctx = setup();
+        let harness = add_harness_maker(&mut ctx);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = harness_make_ix(&ctx, &harness, &taker_pubkey, 100_000_000, 500_000_000);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+
+        // the authority never held lamports, the payer funded the escrow and its vault
+        assert!(ctx.svm.get_account(&harness.authority).is_none());
+        let account = ctx.svm.get_account(&harness.escrow).unwrap();
+        let owner = account.owner.to_bytes().into();
+        let escrow = Escrow::try_from_account(&owner, &account.data).unwrap();
+        assert_eq!(escrow.maker().to_bytes(), harness.authority.to_bytes());
+        assert_eq!(token_balance(&ctx.svm, &harness.escrow_ata_a), 500_000_000);
+        assert_eq!(
+            token_balance(&ctx.svm, &harness.authority_ata_a),
+            500_000_000
+        );
+
+        let ix = harness_refund_ix(&ctx, &harness);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert_eq!(
+            token_balance(&ctx.svm, &harness.authority_ata_a),
+            1_000_000_000
+        );
+        assert!(ctx.svm.get_account(&harness.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_cpi_take_with_pda_taker() {
+        let mut ctx = setup();
+        let harness = add_harness_maker(&mut ctx);
+        make_instruction(&mut ctx, false);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = harness_take_ix(&ctx, &harness);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+
+        assert_eq!(
+            token_balance(&ctx.svm, &harness.authority_ata_a),
+            1_500_000_000
+        );
+        assert_eq!(
+            token_balance(&ctx.svm, &harness.authority_ata_b),
+            900_000_000
+        );
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_make_with_precreated_vault() {
+        let mut ctx = setup();
+        // anyone can create the vault ATA ahead of the maker
+        CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.taker, &ctx.mint_a)
+            .owner(&ctx.escrow)
+            .send()
+            .unwrap();
+
+        make_instruction(&mut ctx, false);
+        take_instruction(&mut ctx);
+    }
+
+    #[test]
+    pub fn test_take_creates_missing_token_accounts() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        // the maker has no mint_b account and the recipient no mint_a account yet
+        let maker_pubkey = ctx.maker.pubkey();
+        let close_ix = spl_token::instruction::close_account(
+            &TOKEN_PROGRAM_ID,
+            &ctx.maker_ata_b,
+            &maker_pubkey,
+            &maker_pubkey,
+            &[],
+        )
+        .unwrap();
+        send_transaction(&mut ctx.svm, close_ix, &[&ctx.maker], &maker_pubkey);
+
+        let recipient = Keypair::new().pubkey();
+        let recipient_ata_a =
+            spl_associated_token_account::get_associated_token_address(&recipient, &ctx.mint_a);
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_for_ix(&ctx, &recipient, &recipient_ata_a);
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &recipient_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+    }
+
+    #[test]
+    pub fn test_refund_creates_missing_maker_account() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        // empty and close the maker's mint_a account
+        let maker_pubkey = ctx.maker.pubkey();
+        let transfer_ix = spl_token::instruction::transfer(
+            &TOKEN_PROGRAM_ID,
+            &ctx.maker_ata_a,
+            &ctx.taker_ata_a,
+            &maker_pubkey,
+            &[],
+            500_000_000,
+        )
+        .unwrap();
+        send_transaction(&mut ctx.svm, transfer_ix, &[&ctx.maker], &maker_pubkey);
+        let close_ix = spl_token::instruction::close_account(
+            &TOKEN_PROGRAM_ID,
+            &ctx.maker_ata_a,
+            &maker_pubkey,
+            &maker_pubkey,
+            &[],
+        )
+        .unwrap();
+        send_transaction(&mut ctx.svm, close_ix, &[&ctx.maker], &maker_pubkey);
+
+        let mut ix = refund_ix(&ctx);
+        ix.accounts
+            .push(AccountMeta::new_readonly(ctx.mint_a, false));
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_lifecycle_events() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let taker_pubkey = ctx.taker.pubkey();
+
+        let make_ix = make_with_options_ix(&ctx, None, &[]);
+        let result = try_send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey)
+            .expect("Make should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EscrowEventKind::Make);
+        assert_eq!(events[0].escrow.to_bytes(), ctx.escrow.to_bytes());
+        assert_eq!(events[0].maker.to_bytes(), maker_pubkey.to_bytes());
+        assert_eq!(events[0].taker.to_bytes(), [0u8; 32]);
+        assert_eq!(events[0].mint_a.to_bytes(), ctx.mint_a.to_bytes());
+        assert_eq!(events[0].mint_b.to_bytes(), ctx.mint_b.to_bytes());
+        assert_eq!(events[0].amount_to_give, 500_000_000);
+        assert_eq!(events[0].amount_to_receive, 100_000_000);
+
+        let take_ix = take_ix(&ctx);
+        let result = try_send_transaction(&mut ctx.svm, take_ix, &[&ctx.taker], &taker_pubkey)
+            .expect("Take should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EscrowEventKind::Take);
+        assert_eq!(events[0].taker.to_bytes(), taker_pubkey.to_bytes());
+        assert_eq!(events[0].amount_to_give, 500_000_000);
+    }
+
+    #[test]
+    pub fn test_refund_event() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let refund_ix = refund_ix(&ctx);
+        let result = try_send_transaction(&mut ctx.svm, refund_ix, &[&ctx.maker], &maker_pubkey)
+            .expect("Refund should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EscrowEventKind::Refund);
+        assert_eq!(events[0].escrow.to_bytes(), ctx.escrow.to_bytes());
+        assert_eq!(events[0].amount_to_give, 500_000_000);
+
+        // an unknown version is rejected rather than misread
+        let mut bytes = events[0].to_bytes();
+        bytes[0] = 0;
+        assert!(EscrowEvent::try_from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    pub fn test_settlement_events() {
+        let mut ctx = setup();
+        let taker_pubkey = ctx.taker.pubkey();
+        make_vesting_instruction(&mut ctx, 0, 1_000);
+        take_instruction(&mut ctx);
+
+        warp_clock(&mut ctx.svm, 250);
+        let ix = claim_ix(&ctx);
+        let result = try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey)
+            .expect("Claim should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EscrowEventKind::Claim);
+        assert_eq!(events[0].taker.to_bytes(), taker_pubkey.to_bytes());
+        assert_eq!(events[0].amount_to_give, 125_000_000);
+        assert_eq!(events[0].amount_to_receive, 0);
+
+        // a reversed trade is logged after its Take
+        let mut ctx = setup();
+        make_arbitrated_instruction(&mut ctx);
+        take_arbitrated_instruction(&mut ctx);
+
+        let ix = arbitrate_ix(&ctx, false);
+        let result =
+            try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker, &ctx.arbiter], &taker_pubkey)
+                .expect("Reverse should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EscrowEventKind::Reverse);
+        assert_eq!(events[0].escrow.to_bytes(), ctx.escrow.to_bytes());
+        assert_eq!(events[0].taker.to_bytes(), taker_pubkey.to_bytes());
+        assert_eq!(events[0].amount_to_give, 500_000_000);
+        assert_eq!(events[0].amount_to_receive, 100_000_000);
+    }
+
+    #[test]
+    pub fn test_match_events() {
+        let mut ctx = setup();
+        let a = add_maker(&mut ctx);
+        let b = add_maker(&mut ctx);
+        make_for_instruction(&mut ctx, &a, true, 100_000_000, 200_000_000);
+        make_for_instruction(&mut ctx, &b, false, 200_000_000, 150_000_000);
+
+        let keeper_pubkey = ctx.taker.pubkey();
+        let ix = match_ix(&a, &b, Some(&ctx.taker_ata_b));
+        let result = try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &keeper_pubkey)
+            .expect("Match should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.kind == EscrowEventKind::Match));
+
+        // the keeper's surplus isn't part of what a received
+        assert_eq!(events[0].escrow.to_bytes(), a.escrow.to_bytes());
+        assert_eq!(events[0].taker.to_bytes(), b.keypair.pubkey().to_bytes());
+        assert_eq!(events[0].amount_to_give, 200_000_000);
+        assert_eq!(events[0].amount_to_receive, 100_000_000);
+
+        assert_eq!(events[1].escrow.to_bytes(), b.escrow.to_bytes());
+        assert_eq!(events[1].mint_a.to_bytes(), ctx.mint_b.to_bytes());
+        assert_eq!(events[1].amount_to_give, 150_000_000);
+        assert_eq!(events[1].amount_to_receive, 200_000_000);
+    }
+
+    #[test]
+    pub fn test_client_make_take() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let taker_pubkey = ctx.taker.pubkey();
+
+        let make_ix = client::make_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+        );
+        assert_eq!(make_ix.accounts[1].pubkey, ctx.escrow);
+        assert_eq!(make_ix.accounts[5].pubkey, ctx.escrow_ata_a);
+        assert!(!make_ix.accounts[2].is_writable);
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        let take_ix = client::take_ix(&taker_pubkey, &maker_pubkey, &ctx.mint_a, &ctx.mint_b);
+        send_transaction(&mut ctx.svm, take_ix, &[&ctx.taker], &taker_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+    }
+
+    #[test]
+    pub fn test_client_refund() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+
+        let make_ix = client::make_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+        );
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        let refund_ix = client::refund_ix(&maker_pubkey, &ctx.mint_a);
+        send_transaction(&mut ctx.svm, refund_ix, &[&ctx.maker], &maker_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_client_make_v2() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+
+        let make_ix = client::make_v2_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+        );
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.escrow_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_idl_is_up_to_date() {
+        // the anchor feature changes the discriminators, so it has its own IDL file
+        let path = if cfg!(feature = "anchor") {
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/idl/pinocchio_escrow.anchor.json"
+            )
+        } else {
+            concat!(env!("CARGO_MANIFEST_DIR"), "/idl/pinocchio_escrow.json")
+        };
+        let generated = idl::idl();
+        if std::env::var("UPDATE_IDL").is_ok() {
+            let json = serde_json::to_string_pretty(&generated).unwrap();
+            std::fs::write(path, json + "\n").unwrap();
+        }
+
+        let committed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(
+            committed, generated,
+            "{path} is stale, rerun the tests with UPDATE_IDL=1"
+        );
+    }
+
+    #[test]
+    pub fn test_instruction_codec_round_trip() {
+        let make = MakeArgs {
+            data: MakeInstructionData {
+                bump: 254,
+                amount_to_receive: 100,
+                amount_to_give: 500,
+            },
+            options: Some(MakeOptions {
+                approval_threshold: 1,
+                approvals_bump: 253,
+                ..Default::default()
+            }),
+            approvers: &[7u8; 64],
+        };
+        let instructions = [
+            EscrowInstruction::Make(make),
+            EscrowInstruction::Take,
+            EscrowInstruction::Refund,
+            EscrowInstruction::MakeV2(wincode_v2::make::MakeInstructionData {
+                bump: 254,
+                amount_to_receive: [1u8; 64],
+                amount_to_give: [2u8; 64],
+            }),
+            EscrowInstruction::TakeV2,
+            EscrowInstruction::RefundV2,
+            EscrowInstruction::Claim,
+            EscrowInstruction::Release,
+            EscrowInstruction::Reverse,
+            EscrowInstruction::Approve,
+            EscrowInstruction::MakeHtlc(MakeHtlcInstructionData {
+                bump: 254,
+                amount_to_give: 500,
+                hashlock: [3u8; 32],
+                timeout: -1,
+                recipient: [4u8; 32],
+            }),
+            EscrowInstruction::Redeem {
+                preimage: b"secret",
+            },
+            EscrowInstruction::TakeMany,
+            EscrowInstruction::Match,
+            EscrowInstruction::InitMarket { bump: 255 },
+            EscrowInstruction::BestOffers { n: 5 },
+            EscrowInstruction::SetDelegate {
+                delegate: [5u8; 32],
+            },
+            EscrowInstruction::TakeFor,
+            EscrowInstruction::Migrate,
+            EscrowInstruction::MakeWithExtensions {
+                extensions: &[1, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                make: MakeArgs {
+                    options: None,
+                    approvers: &[],
+                    ..make
+                },
+            },
+            EscrowInstruction::MakeWithPayer(make),
+        ];
+
+        for instruction in &instructions {
+            let data = instruction.pack();
+            assert_eq!(data[0], instruction.discriminator() as u8);
+            assert_eq!(&EscrowInstruction::unpack(&data).unwrap(), instruction);
+        }
+
+        // one sample per discriminator the program accepts
+        for byte in 0..=u8::MAX {
+            assert_eq!(
+                EscrowInstrctions::try_from(&byte).is_ok(),
+                instructions
+                    .iter()
+                    .any(|ix| ix.discriminator() as u8 == byte),
+            );
+        }
+
+        // the wire format is unchanged: raw fields after the discriminator byte
+        let data = EscrowInstruction::Make(MakeArgs {
+            options: None,
+            ..make
+        })
+        .pack();
+        assert_eq!(
+            data,
+            [
+                &[0u8, 254][..],
+                &100u64.to_le_bytes()[..],
+                &500u64.to_le_bytes()[..]
+            ]
+            .concat()
+        );
+        assert!(EscrowInstruction::unpack(&[]).is_err());
+        assert!(EscrowInstruction::unpack(&[EscrowInstrctions::Take as u8, 0]).is_err());
+        assert!(EscrowInstruction::unpack(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    pub fn test_anchor_discriminators() {
+        let sighash = |preimage: String| {
+            solana_sha256_hasher::hash(preimage.as_bytes()).to_bytes()[..8].to_vec()
+        };
+
+        for ix in idl::INSTRUCTIONS {
+            let tag = EscrowInstrctions::try_from(&ix.discriminator).unwrap();
+            assert_eq!(
+                tag.sighash().to_vec(),
+                sighash(format!("global:{}", ix.name))
+            );
+
+            let data = [&tag.sighash()[..], &[1u8, 2, 3][..]].concat();
+            assert_eq!(
+                EscrowInstrctions::from_sighash(&data),
+                Some((tag, &[1u8, 2, 3][..]))
+            );
+        }
+        assert_eq!(idl::INSTRUCTIONS.len(), EscrowInstrctions::ALL.len());
+
+        for account_type in &AccountType::ALL[1..] {
+            assert_eq!(
+                account_type.anchor_discriminator().to_vec(),
+                sighash(format!("account:{account_type:?}"))
+            );
+        }
+    }
+
+    #[cfg(feature = "anchor")]
+    #[test]
+    pub fn test_anchor_make_and_take() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+
+        let mut ix = make_with_options_ix(&ctx, None, &[]);
+        ix.data = EscrowInstruction::unpack(&ix.data).unwrap().pack_anchor();
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(
+            account.data[..8],
+            AccountType::Escrow.anchor_discriminator()
+        );
+
+        let mut ix = take_ix(&ctx);
+        ix.data = EscrowInstruction::Take.pack_anchor();
+        let taker_pubkey = ctx.taker.pubkey();
+        send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[cfg(feature = "anchor")]
+    #[test]
+    pub fn test_anchor_tagged_set_delegate() {
+        let take_many = EscrowInstrctions::TakeMany.sighash();
+        assert_eq!(take_many[0], EscrowInstrctions::SetDelegate as u8);
+
+        let delegate = [7u8; 32];
+        let ix = EscrowInstruction::SetDelegate { delegate };
+        assert_eq!(EscrowInstruction::unpack(&ix.pack()).unwrap(), ix);
+
+        // a delegate completing the TakeMany sighash fails on the trailing bytes instead
+        // of decoding as TakeMany, and still goes through with the SetDelegate sighash
+        let mut delegate = [7u8; 32];
+        delegate[..7].copy_from_slice(&take_many[1..]);
+        let ix = EscrowInstruction::SetDelegate { delegate };
+        assert!(EscrowInstruction::unpack(&ix.pack()).is_err());
+        assert_eq!(EscrowInstruction::unpack(&ix.pack_anchor()).unwrap(), ix);
+    }
+
+    #[test]
+    pub fn test_idl_covers_program() {
+        for byte in 0..=u8::MAX {
+            if EscrowInstrctions::try_from(&byte).is_ok() {
+                assert!(
+                    idl::INSTRUCTIONS.iter().any(|ix| ix.discriminator == byte),
+                    "instruction {byte} is missing from the IDL"
+                );
+            }
+        }
+
+        let size = |fields: &[idl::IdlField]| -> usize {
+            fields.iter().map(|f| f.ty.size().unwrap()).sum()
+        };
+        assert_eq!(size(idl::ESCROW_FIELDS), size_of::<Escrow>());
+        assert_eq!(size(idl::WINCODE_ESCROW_FIELDS), size_of::<WincodeEscrow>());
+        assert_eq!(size(idl::APPROVALS_FIELDS), size_of::<Approvals>());
+        assert_eq!(size(idl::MARKET_FIELDS), size_of::<Market>());
+        assert_eq!(size(idl::MARKET_ENTRY_FIELDS), size_of::<MarketEntry>());
+
+        for (code, (error, _)) in idl::ERRORS.iter().enumerate() {
+            assert_eq!(*error as usize, code);
+        }
+    }
+
+    #[test]
+    pub fn test_idl_matches_instructions() {
+        // same instructions, in tag order, named after the tags
+        let tags: Vec<u8> = idl::INSTRUCTIONS
+            .iter()
+            .map(|ix| ix.discriminator)
+            .collect();
+        let all: Vec<u8> = EscrowInstrctions::ALL
+            .iter()
+            .map(|tag| *tag as u8)
+            .collect();
+        assert_eq!(tags, all);
+        for (ix, tag) in idl::INSTRUCTIONS.iter().zip(EscrowInstrctions::ALL) {
+            let mut name = String::new();
+            for c in format!("{tag:?}").chars() {
+                if c.is_ascii_uppercase() && !name.is_empty() {
+                    name.push('_');
+                }
+                name.push(c.to_ascii_lowercase());
+            }
+            assert_eq!(ix.name, name);
+        }
+
+        // data laid out from the IDL args decodes, and so does every optional cut
+        for ix in idl::INSTRUCTIONS {
+            let mut data = vec![ix.discriminator];
+            let mut cuts = vec![];
+            for arg in ix.args {
+                if arg.optional {
+                    cuts.push(data.len());
+                }
+                match arg.ty {
+                    idl::IdlType::Bytes => data.extend_from_slice(&0u32.to_le_bytes()),
+                    idl::IdlType::Trailing(_) => {}
+                    ty => data.resize(data.len() + ty.size().unwrap(), 0),
+                }
+            }
+
+            assert!(EscrowInstruction::unpack(&data).is_ok(), "{}", ix.name);
+            for cut in cuts {
+                assert!(
+                    EscrowInstruction::unpack(&data[..cut]).is_ok(),
+                    "{} cut at {cut}",
+                    ix.name
+                );
+            }
+            if !ix.args.is_empty() {
+                assert!(
+                    EscrowInstruction::unpack(&data[..data.len() - 1]).is_err(),
+                    "{}",
+                    ix.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_idl_encoded_make_with_approvals() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let approver = Keypair::new();
+        let (approvals, approvals_bump) = Pubkey::find_program_address(
+            &[b"approvals".as_ref(), ctx.escrow.as_ref()],
+            &program_id(),
+        );
+
+        let ix = idl_ix(
+            "make",
+            &[
+                ("maker", maker_pubkey),
+                ("escrow", ctx.escrow),
+                ("mint_a", ctx.mint_a),
+                ("mint_b", ctx.mint_b),
+                ("maker_ata", ctx.maker_ata_a),
+                ("escrow_ata", ctx.escrow_ata_a),
+                ("system_program", ctx.system_program),
+                ("token_program", TOKEN_PROGRAM_ID),
+                ("associated_token_program", ctx.associated_token_program),
+                ("approvals", approvals),
+            ],
+            serde_json::json!({
+                "bump": ctx.escrow_bump,
+                "amount_to_receive": 100_000_000u64,
+                "amount_to_give": 500_000_000u64,
+                "vesting_cliff": 0u64,
+                "vesting_duration": 0u64,
+                "arbiter": Pubkey::default().to_string(),
+                "approval_threshold": 1,
+                "approvals_bump": approvals_bump,
+                "approvers": [approver.pubkey().to_string()],
+            }),
+        );
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(
+            Escrow::try_from_bytes(&account.data)
+                .unwrap()
+                .approval_threshold,
+            1
+        );
+        assert!(ctx.svm.get_account(&approvals).is_some());
+    }
+
+    #[test]
+    pub fn test_escrow_try_from_bytes() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        let owner = account.owner.to_bytes().into();
+        let escrow = Escrow::try_from_account(&owner, &account.data).unwrap();
+        assert_eq!(escrow.maker().to_bytes(), ctx.maker.pubkey().to_bytes());
+        assert_eq!(escrow.mint_a().to_bytes(), ctx.mint_a.to_bytes());
+        assert_eq!(escrow.amount_to_give(), 500_000_000);
+        assert_eq!(escrow.amount_to_receive(), 100_000_000);
+        assert_eq!(escrow.bump, ctx.escrow_bump);
+
+        // wrong owner or a truncated account is rejected
+        let not_owner = ctx.maker.pubkey().to_bytes().into();
+        assert!(Escrow::try_from_account(&not_owner, &account.data).is_err());
+        assert!(Escrow::try_from_bytes(&account.data[..Escrow::LEN - 1]).is_err());
+
+        let json = serde_json::to_value(escrow).unwrap();
+        assert_eq!(json["maker"], ctx.maker.pubkey().to_string());
+        assert_eq!(json["amount_to_give"], 500_000_000);
+        assert_eq!(json["listed"], false);
+    }
+
+    #[test]
+    pub fn test_wincode_escrow_round_trip() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(account.data.len(), WincodeEscrow::LEN);
+
+        let escrow = WincodeEscrow::try_from_bytes(&account.data).unwrap();
+        assert_eq!(escrow.maker, ctx.maker.pubkey().to_bytes());
+        assert_eq!(escrow.mint_b, ctx.mint_b.to_bytes());
+        assert_eq!(escrow.amount_to_receive(), 100_000_000);
+        assert_eq!(escrow.amount_to_give(), 500_000_000);
+        assert_eq!(escrow.bump, ctx.escrow_bump);
+
+        // encoding the decoded state gives back the exact account bytes
+        let encoded = wincode::serialize(&escrow).unwrap();
+        assert_eq!(encoded, account.data);
+        assert_eq!(WincodeEscrow::try_from_bytes(&encoded).unwrap(), escrow);
+    }
+
+    #[test]
+    pub fn test_take_dispatches_v2_escrow() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+        take_instruction(&mut ctx);
+
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_refund_dispatches_v2_escrow() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(
+            account.data[..DISCRIMINATOR_LEN],
+            AccountType::WincodeEscrow.discriminator()
+        );
+        assert!(Escrow::try_from_bytes(&account.data).is_err());
+
+        refund_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_migrate_legacy_escrow() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, false);
+
+        // rewrite the escrow the way it was stored before the discriminator
+        let mut account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(
+            account.data[..DISCRIMINATOR_LEN],
+            AccountType::Escrow.discriminator()
+        );
+        account.data.drain(..DISCRIMINATOR_LEN);
+        account.lamports = ctx
+            .svm
+            .minimum_balance_for_rent_exemption(account.data.len());
+        ctx.svm.set_account(ctx.escrow, account).unwrap();
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        // only the maker stored in the escrow can migrate it
+        let ix = client::migrate_ix(&taker_pubkey, &ctx.escrow);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let ix = client::migrate_ix(&maker_pubkey, &ctx.escrow);
+        send_transaction(&mut ctx.svm, ix.clone(), &[&ctx.maker], &maker_pubkey);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(account.data.len(), Escrow::LEN);
+        assert_eq!(
+            account.lamports,
+            ctx.svm.minimum_balance_for_rent_exemption(Escrow::LEN)
+        );
+        let escrow = Escrow::try_from_bytes(&account.data).unwrap();
+        assert_eq!(escrow.maker().to_bytes(), ctx.maker.pubkey().to_bytes());
+        assert_eq!(escrow.amount_to_give(), 500_000_000);
+
+        // a current escrow can't be migrated again
+        ctx.svm.expire_blockhash();
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+
+        ctx.svm.expire_blockhash();
+        take_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_migrate_rejects_legacy_v2_escrow() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        // MakeV2 accounts were stored at the legacy v1 length, cutting into amount_to_receive
+        let mut account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        account.data.drain(..DISCRIMINATOR_LEN);
+        account.data.truncate(Escrow::LEGACY_MIN_LEN);
+        ctx.svm.set_account(ctx.escrow, account).unwrap();
+
+        let maker_pubkey = ctx.maker.pubkey();
+        let ix = client::migrate_ix(&maker_pubkey, &ctx.escrow);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey).is_err());
+        assert_eq!(
+            ctx.svm.get_account(&ctx.escrow).unwrap().data.len(),
+            Escrow::LEGACY_MIN_LEN
+        );
+    }
+
+    #[test]
+    pub fn test_take_v2_and_refund_v2_instructions() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        let mut ix = take_ix(&ctx);
+        ix.data = EscrowInstruction::TakeV2.pack();
+        let taker_pubkey = ctx.taker.pubkey();
+        let result = try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey)
+            .expect("TakeV2 should succeed");
+        let events = escrow_events(&result.logs);
+        assert_eq!(events[0].kind, EscrowEventKind::Take);
+        assert_eq!(events[0].amount_to_give, 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_b), 100_000_000);
+
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        let mut ix = refund_ix(&ctx);
+        ix.data = EscrowInstruction::RefundV2.pack();
+        let maker_pubkey = ctx.maker.pubkey();
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+        assert_eq!(token_balance(&ctx.svm, &ctx.maker_ata_a), 1_000_000_000);
+        assert!(ctx.svm.get_account(&ctx.escrow_ata_a).is_none());
+    }
+
+    #[test]
+    pub fn test_take_v2_rejects_junk_mint_b() {
+        let mut ctx = setup();
+        make_instruction(&mut ctx, true);
+
+        // a worthless mint the taker controls, with accounts for both parties
+        let junk = CreateMint::new(&mut ctx.svm, &ctx.taker)
+            .decimals(6)
+            .authority(&ctx.taker.pubkey())
+            .send()
+            .unwrap();
+        let maker_junk = CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.taker, &junk)
+            .owner(&ctx.maker.pubkey())
+            .send()
+            .unwrap();
+        let taker_junk = CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.taker, &junk)
+            .owner(&ctx.taker.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut ctx.svm, &ctx.taker, &junk, &taker_junk, 1_000_000_000)
+            .send()
+            .unwrap();
+
+        let taker_pubkey = ctx.taker.pubkey();
+        for (taker_ata_b, maker_ata_b) in [
+            (taker_junk, maker_junk),
+            (ctx.taker_ata_b, maker_junk),
+            (taker_junk, ctx.maker_ata_b),
+        ] {
+            let mut ix = take_ix(&ctx);
+            ix.accounts[6] = AccountMeta::new(taker_ata_b, false);
+            ix.accounts[8] = AccountMeta::new(maker_ata_b, false);
+            assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+        }
+        assert_eq!(token_balance(&ctx.svm, &ctx.escrow_ata_a), 500_000_000);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 0);
+    }
+
+    #[test]
+    pub fn test_make_with_extensions() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let taker_pubkey = ctx.taker.pubkey();
+        let expires_at = (now(&ctx.svm) + 1_000).to_le_bytes();
+
+        let make_ix = client::make_with_extensions_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+            &[
+                (ExtensionType::Expiry, &expires_at[..]),
+                (ExtensionType::DesignatedTaker, taker_pubkey.as_ref()),
+                (ExtensionType::Memo, &b"otc desk"[..]),
+            ],
+        );
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        // only the requested entries are allocated after the fixed layout
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        assert_eq!(
+            account.data.len(),
+            Escrow::LEN + (4 + 8) + (4 + 32) + (4 + 8)
+        );
+        let extensions = escrow_extensions(&account.data);
+        let memo = get_extension(extensions, ExtensionType::Memo).unwrap();
+        assert_eq!(memo, Some(&b"otc desk"[..]));
+        let designated = get_extension(extensions, ExtensionType::DesignatedTaker).unwrap();
+        assert_eq!(designated, Some(taker_pubkey.as_ref()));
+        assert_eq!(
+            Escrow::try_from_bytes(&account.data)
+                .unwrap()
+                .amount_to_give(),
+            500_000_000
+        );
+
+        take_instruction(&mut ctx);
+        assert_eq!(token_balance(&ctx.svm, &ctx.taker_ata_a), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_idl_encoded_make_with_extensions() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+
+        // a single memo entry: type 3, length 8, value
+        let mut entries = vec![3, 0, 8, 0];
+        entries.extend_from_slice(b"otc desk");
+
+        let ix = idl_ix(
+            "make_with_extensions",
+            &[
+                ("maker", maker_pubkey),
+                ("escrow", ctx.escrow),
+                ("mint_a", ctx.mint_a),
+                ("mint_b", ctx.mint_b),
+                ("maker_ata", ctx.maker_ata_a),
+                ("escrow_ata", ctx.escrow_ata_a),
+                ("system_program", ctx.system_program),
+                ("token_program", TOKEN_PROGRAM_ID),
+                ("associated_token_program", ctx.associated_token_program),
+            ],
+            serde_json::json!({
+                "extensions": entries,
+                "bump": ctx.escrow_bump,
+                "amount_to_receive": 100_000_000u64,
+                "amount_to_give": 500_000_000u64,
+            }),
+        );
+        send_transaction(&mut ctx.svm, ix, &[&ctx.maker], &maker_pubkey);
+
+        let account = ctx.svm.get_account(&ctx.escrow).unwrap();
+        let memo = get_extension(escrow_extensions(&account.data), ExtensionType::Memo).unwrap();
+        assert_eq!(memo, Some(&b"otc desk"[..]));
+        let escrow = Escrow::try_from_bytes(&account.data).unwrap();
+        assert_eq!(escrow.amount_to_receive(), 100_000_000);
+        assert_eq!(escrow.amount_to_give(), 500_000_000);
+    }
+
+    #[test]
+    pub fn test_designated_taker_and_expiry() {
+        let mut ctx = setup();
+        let maker_pubkey = ctx.maker.pubkey();
+        let taker_pubkey = ctx.taker.pubkey();
+        let arbiter_pubkey = ctx.arbiter.pubkey();
+        let expires_at = (now(&ctx.svm) + 1_000).to_le_bytes();
+
+        let make_ix = client::make_with_extensions_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+            &[
+                (ExtensionType::DesignatedTaker, arbiter_pubkey.as_ref()),
+                (ExtensionType::Expiry, &expires_at[..]),
+            ],
+        );
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+
+        // a duplicate entry or an expiry in the past is rejected at Make
+        let mut ctx = setup();
+        let past = (now(&ctx.svm) - 1).to_le_bytes();
+        for extensions in [
+            vec![
+                (ExtensionType::Memo, &b"a"[..]),
+                (ExtensionType::Memo, &b"b"[..]),
+            ],
+            vec![(ExtensionType::Expiry, &past[..])],
+        ] {
+            let make_ix = client::make_with_extensions_ix(
+                &maker_pubkey,
+                &ctx.mint_a,
+                &ctx.mint_b,
+                100_000_000,
+                500_000_000,
+                &extensions,
+            );
+            assert!(
+                try_send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey).is_err()
+            );
+        }
+
+        let make_ix = client::make_with_extensions_ix(
+            &maker_pubkey,
+            &ctx.mint_a,
+            &ctx.mint_b,
+            100_000_000,
+            500_000_000,
+            &[(ExtensionType::Expiry, &expires_at[..])],
+        );
+        send_transaction(&mut ctx.svm, make_ix, &[&ctx.maker], &maker_pubkey);
+
+        warp_clock(&mut ctx.svm, 1_000);
+        let ix = take_ix(&ctx);
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+    }
+
+    #[test]
+    pub fn test_state_guards_check_owner() {
+        let mut ctx = setup();
+        let market = init_market_instruction(&mut ctx);
+
+        // a byte for byte copy of the market owned by another program is not a market
+        let mut account = ctx.svm.get_account(&market).unwrap();
+        account.owner = ctx.system_program;
+        let spoofed = Pubkey::new_unique();
+        ctx.svm.set_account(spoofed, account).unwrap();
+
+        let taker_pubkey = ctx.taker.pubkey();
+        let ix = Instruction {
+            program_id: program_id(),
+            accounts: vec![AccountMeta::new_readonly(spoofed, false)],
+            data: EscrowInstruction::BestOffers { n: 5 }.pack(),
+        };
+        assert!(try_send_transaction(&mut ctx.svm, ix, &[&ctx.taker], &taker_pubkey).is_err());
+        assert!(best_offers(&mut ctx, &market, 5).is_empty());
+    }
 }
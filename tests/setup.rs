@@ -1,12 +1,19 @@
-use pinocchio_escrow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pinocchio_escrow::{
+    self,
+    client::{escrow_vault_address, find_escrow_address},
+    events::EscrowEvent,
+};
 use std::path::PathBuf;
 
-use litesvm::LiteSVM;
+use litesvm::{types::TransactionResult, LiteSVM};
 use litesvm_token::{
+    get_spl_account,
     spl_token::{self},
     CreateAssociatedTokenAccount, CreateMint, MintTo,
 };
 use solana_sdk::{
+    clock::Clock,
     message::{Instruction, Message},
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
@@ -17,6 +24,10 @@ use solana_sdk::{
 
 pub const TOKEN_PROGRAM_ID: Pubkey = spl_token::ID;
 pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub const CPI_HARNESS_PROGRAM_ID: &str = "3oPwyRUHbTWFrQfrFd6AbcE4MsqkkUwQbRwsXLwKNHVd";
+
+/// Where `cargo build-sbf` puts the escrow and the `tests/cpi-harness` program
+const SBF_OUT_DIR: &str = "/Users/karthikeya/Documents/Security-Research/Learning/Turbin3 /Karthikeya_Q126Accel_Work/pinocchio-escrow/target/sbpf-solana-solana/release";
 
 pub fn program_id() -> Pubkey {
     Pubkey::from(pinocchio_escrow::ID)
@@ -26,6 +37,7 @@ pub struct TestContext {
     pub svm: LiteSVM,
     pub maker: Keypair,
     pub taker: Keypair,
+    pub arbiter: Keypair,
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
     pub maker_ata_a: Pubkey,
@@ -35,6 +47,7 @@ pub struct TestContext {
     pub escrow: Pubkey,
     pub escrow_bump: u8,
     pub escrow_ata_a: Pubkey,
+    pub escrow_ata_b: Pubkey,
     pub associated_token_program: Pubkey,
     pub system_program: Pubkey,
 }
@@ -43,6 +56,7 @@ pub fn setup() -> TestContext {
     let mut svm = LiteSVM::new();
     let maker = Keypair::new();
     let taker = Keypair::new();
+    let arbiter = Keypair::new();
 
     svm.airdrop(&maker.pubkey(), 10 * LAMPORTS_PER_SOL)
         .expect("Airdrop failed for maker");
@@ -50,9 +64,7 @@ pub fn setup() -> TestContext {
         .expect("Airdrop failed for taker");
 
     // Load program SO file
-    let so_path = PathBuf::from(
-            "/Users/karthikeya/Documents/Security-Research/Learning/Turbin3 /Karthikeya_Q126Accel_Work/pinocchio-escrow/target/sbpf-solana-solana/release/pinocchio_escrow.so",
-        );
+    let so_path = PathBuf::from(SBF_OUT_DIR).join("pinocchio_escrow.so");
     let program_data = std::fs::read(so_path).expect("Failed to read program SO file");
     svm.add_program(program_id(), &program_data)
         .expect("Failed to add program");
@@ -92,12 +104,10 @@ pub fn setup() -> TestContext {
         .unwrap();
 
     // Derive escrow PDA and vault
-    let (escrow, escrow_bump) = Pubkey::find_program_address(
-        &[b"escrow".as_ref(), maker.pubkey().as_ref()],
-        &program_id(),
-    );
+    let (escrow, escrow_bump) = find_escrow_address(&maker.pubkey());
 
-    let escrow_ata_a = spl_associated_token_account::get_associated_token_address(&escrow, &mint_a);
+    let escrow_ata_a = escrow_vault_address(&escrow, &mint_a);
+    let escrow_ata_b = escrow_vault_address(&escrow, &mint_b);
 
     // Mint tokens
     MintTo::new(&mut svm, &maker, &mint_a, &maker_ata_a, 1_000_000_000)
@@ -115,6 +125,7 @@ pub fn setup() -> TestContext {
         svm,
         maker,
         taker,
+        arbiter,
         mint_a,
         mint_b,
         maker_ata_a,
@@ -124,11 +135,125 @@ pub fn setup() -> TestContext {
         escrow,
         escrow_bump,
         escrow_ata_a,
+        escrow_ata_b,
         associated_token_program,
         system_program,
     }
 }
 
+/// Another funded maker sharing the context's mints
+pub struct ExtraMaker {
+    pub keypair: Keypair,
+    pub ata_a: Pubkey,
+    pub ata_b: Pubkey,
+    pub escrow: Pubkey,
+    pub escrow_bump: u8,
+    pub escrow_ata_a: Pubkey,
+    pub escrow_ata_b: Pubkey,
+}
+
+pub fn add_maker(ctx: &mut TestContext) -> ExtraMaker {
+    let keypair = Keypair::new();
+    ctx.svm
+        .airdrop(&keypair.pubkey(), 10 * LAMPORTS_PER_SOL)
+        .expect("Airdrop failed for extra maker");
+
+    let ata_a = CreateAssociatedTokenAccount::new(&mut ctx.svm, &keypair, &ctx.mint_a)
+        .owner(&keypair.pubkey())
+        .send()
+        .unwrap();
+    let ata_b = CreateAssociatedTokenAccount::new(&mut ctx.svm, &keypair, &ctx.mint_b)
+        .owner(&keypair.pubkey())
+        .send()
+        .unwrap();
+
+    MintTo::new(&mut ctx.svm, &ctx.maker, &ctx.mint_a, &ata_a, 1_000_000_000)
+        .send()
+        .unwrap();
+    MintTo::new(&mut ctx.svm, &ctx.taker, &ctx.mint_b, &ata_b, 1_000_000_000)
+        .send()
+        .unwrap();
+
+    let (escrow, escrow_bump) = find_escrow_address(&keypair.pubkey());
+    let escrow_ata_a = escrow_vault_address(&escrow, &ctx.mint_a);
+    let escrow_ata_b = escrow_vault_address(&escrow, &ctx.mint_b);
+
+    ExtraMaker {
+        keypair,
+        ata_a,
+        ata_b,
+        escrow,
+        escrow_bump,
+        escrow_ata_a,
+        escrow_ata_b,
+    }
+}
+
+/// Escrow maker or taker that is a PDA of the CPI harness program, acting through it
+pub struct HarnessMaker {
+    pub program_id: Pubkey,
+    pub authority: Pubkey,
+    pub authority_bump: u8,
+    pub authority_ata_a: Pubkey,
+    pub authority_ata_b: Pubkey,
+    pub escrow: Pubkey,
+    pub escrow_bump: u8,
+    pub escrow_ata_a: Pubkey,
+}
+
+pub fn add_harness_maker(ctx: &mut TestContext) -> HarnessMaker {
+    let program_id = CPI_HARNESS_PROGRAM_ID.parse::<Pubkey>().unwrap();
+    let so_path = PathBuf::from(SBF_OUT_DIR).join("cpi_harness.so");
+    let program_data = std::fs::read(so_path).expect("Failed to read CPI harness SO file");
+    ctx.svm
+        .add_program(program_id, &program_data)
+        .expect("Failed to add CPI harness program");
+
+    let (authority, authority_bump) = Pubkey::find_program_address(&[b"authority"], &program_id);
+
+    let authority_ata_a = CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.maker, &ctx.mint_a)
+        .owner(&authority)
+        .send()
+        .unwrap();
+    MintTo::new(
+        &mut ctx.svm,
+        &ctx.maker,
+        &ctx.mint_a,
+        &authority_ata_a,
+        1_000_000_000,
+    )
+    .send()
+    .unwrap();
+
+    let authority_ata_b = CreateAssociatedTokenAccount::new(&mut ctx.svm, &ctx.taker, &ctx.mint_b)
+        .owner(&authority)
+        .send()
+        .unwrap();
+    MintTo::new(
+        &mut ctx.svm,
+        &ctx.taker,
+        &ctx.mint_b,
+        &authority_ata_b,
+        1_000_000_000,
+    )
+    .send()
+    .unwrap();
+
+    let (escrow, escrow_bump) = find_escrow_address(&authority);
+    let escrow_ata_a = escrow_vault_address(&escrow, &ctx.mint_a);
+
+    HarnessMaker {
+        program_id,
+        authority,
+        authority_bump,
+        authority_ata_a,
+        authority_ata_b,
+        escrow,
+        escrow_bump,
+        escrow_ata_a,
+    }
+}
+
 pub fn send_transaction(svm: &mut LiteSVM, ix: Instruction, signers: &[&Keypair], payer: &Pubkey) {
     let message = Message::new(&[ix], Some(payer));
     let recent_blockhash = svm.latest_blockhash();
@@ -138,3 +263,41 @@ pub fn send_transaction(svm: &mut LiteSVM, ix: Instruction, signers: &[&Keypair]
         .expect("Transaction should succeed");
     println!("CUs Consumed: {}", tx.compute_units_consumed);
 }
+
+pub fn try_send_transaction(
+    svm: &mut LiteSVM,
+    ix: Instruction,
+    signers: &[&Keypair],
+    payer: &Pubkey,
+) -> TransactionResult {
+    let message = Message::new(&[ix], Some(payer));
+    let recent_blockhash = svm.latest_blockhash();
+    let transaction = Transaction::new(signers, message, recent_blockhash);
+    svm.send_transaction(transaction)
+}
+
+pub fn token_balance(svm: &LiteSVM, ata: &Pubkey) -> u64 {
+    get_spl_account::<spl_token::state::Account>(svm, ata)
+        .expect("Token account should exist")
+        .amount
+}
+
+pub fn now(svm: &LiteSVM) -> i64 {
+    svm.get_sysvar::<Clock>().unix_timestamp
+}
+
+pub fn warp_clock(svm: &mut LiteSVM, seconds: i64) {
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp += seconds;
+    svm.set_sysvar::<Clock>(&clock);
+    svm.expire_blockhash();
+}
+
+/// Decodes every escrow event the program logged through `sol_log_data`
+pub fn escrow_events(logs: &[String]) -> Vec<EscrowEvent> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|data| EscrowEvent::try_from_bytes(&data).ok())
+        .collect()
+}